@@ -0,0 +1,500 @@
+// assembles the mnemonics disasm prints back into a rom, so a listing can be edited and rebuilt.
+// One instruction per line, with labels and data, e.g.
+//
+//     start:  LD V0, 0x05      ; comments run to the end of the line
+//             CALL draw
+//             JP start
+//     draw:   LD I, sprite
+//             DRW V0, V0, 2
+//             RET
+//     sprite: DB 0b11000011, 0x3C
+//
+// Numbers are decimal, 0x or # hex or 0b binary, and anywhere an address goes a label can too.
+// Everything is laid out from DEFAULT_START_ADDR
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::disasm::Instruction;
+use crate::DEFAULT_START_ADDR;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    // both count from 1
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// the rom and where each label ended up
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Assembled {
+    pub rom: Vec<u8>,
+    pub labels: HashMap<String, u16>,
+}
+
+pub fn assemble(source: &str) -> Result<Assembled, AsmError> {
+    let statements = parse(source);
+
+    // the first pass only needs sizes, which don't depend on what labels are worth
+    let mut labels = HashMap::new();
+    let mut addr = DEFAULT_START_ADDR as usize;
+    for statement in &statements {
+        for label in &statement.labels {
+            if labels.insert(label.text.to_string(), addr as u16).is_some() {
+                return Err(label.error(format!("{} is already defined", label.text)));
+            }
+        }
+        addr += statement.size();
+        if addr > 0x10000 {
+            return Err(statement.mnemonic.error("the rom doesn't fit in memory"));
+        }
+    }
+
+    let mut rom = Vec::new();
+    for statement in &statements {
+        statement.emit(&labels, &mut rom)?;
+    }
+    Ok(Assembled { rom, labels })
+}
+
+// a word along with where it came from, for errors
+#[derive(Clone, Copy, Debug)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Token<'_> {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+}
+
+struct Statement<'a> {
+    labels: Vec<Token<'a>>,
+    mnemonic: Token<'a>,
+    operands: Vec<Token<'a>>,
+}
+
+enum Operand {
+    V(u8),
+    I,
+    IndirectI,
+    Dt,
+    St,
+    K,
+    F,
+    B,
+    Value(u16),
+}
+
+fn parse(source: &str) -> Vec<Statement<'_>> {
+    let mut statements = Vec::new();
+    let mut labels = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let code = line.split(';').next().unwrap_or("");
+        let mut rest = code;
+        // any number of labels can come before the instruction
+        loop {
+            let trimmed = rest.trim_start();
+            let Some(colon) = trimmed.find(':') else {
+                break;
+            };
+            let name = &trimmed[..colon];
+            if !is_label(name) {
+                break;
+            }
+            labels.push(token(line, name, i));
+            rest = &trimmed[colon + 1..];
+        }
+
+        let rest = rest.trim();
+        if rest.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = match rest.find(char::is_whitespace) {
+            Some(space) => (&rest[..space], rest[space..].trim()),
+            None => (rest, ""),
+        };
+        let operands = if operands.is_empty() {
+            Vec::new()
+        } else {
+            operands
+                .split(',')
+                .map(|operand| token(line, operand.trim(), i))
+                .collect()
+        };
+        statements.push(Statement {
+            labels: std::mem::take(&mut labels),
+            mnemonic: token(line, mnemonic, i),
+            operands,
+        });
+    }
+    // labels at the very end mark the address after everything
+    if !labels.is_empty() {
+        statements.push(Statement {
+            labels,
+            mnemonic: Token {
+                text: "",
+                line: source.lines().count(),
+                column: 1,
+            },
+            operands: Vec::new(),
+        });
+    }
+    statements
+}
+
+fn is_label(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// text is a slice of line, which gives its column
+fn token<'a>(line: &'a str, text: &'a str, index: usize) -> Token<'a> {
+    Token {
+        text,
+        line: index + 1,
+        column: text.as_ptr() as usize - line.as_ptr() as usize + 1,
+    }
+}
+
+impl Statement<'_> {
+    fn size(&self) -> usize {
+        match self.mnemonic.text.to_ascii_uppercase().as_str() {
+            "" => 0,
+            "DB" => self.operands.len(),
+            "DW" => self.operands.len() * 2,
+            _ => 2,
+        }
+    }
+
+    fn emit(&self, labels: &HashMap<String, u16>, rom: &mut Vec<u8>) -> Result<(), AsmError> {
+        let mnemonic = self.mnemonic.text.to_ascii_uppercase();
+        match mnemonic.as_str() {
+            "" => return Ok(()),
+            "DB" => {
+                for operand in &self.operands {
+                    rom.push(byte(operand, value(operand, labels)?)?);
+                }
+                return Ok(());
+            }
+            "DW" => {
+                for operand in &self.operands {
+                    rom.extend_from_slice(&value(operand, labels)?.to_be_bytes());
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let operands = self
+            .operands
+            .iter()
+            .map(|operand| self::operand(operand, labels))
+            .collect::<Result<Vec<_>, _>>()?;
+        let instruction = self.instruction(&mnemonic, &operands)?;
+        rom.extend_from_slice(&instruction.encode().to_be_bytes());
+        Ok(())
+    }
+
+    fn instruction(&self, mnemonic: &str, operands: &[Operand]) -> Result<Instruction, AsmError> {
+        use Operand::*;
+        let addr = |nnn: u16, i: usize| {
+            if nnn > 0xFFF {
+                Err(self.operands[i].error(format!("{:#X} is past 0xFFF", nnn)))
+            } else {
+                Ok(nnn)
+            }
+        };
+        let nn = |value: u16, i: usize| byte(&self.operands[i], value);
+
+        let instruction = match (mnemonic, operands) {
+            ("NOP", []) => Instruction::Nop,
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("LOW", []) => Instruction::Low,
+            ("HIGH", []) => Instruction::High,
+            ("JP", [Value(nnn)]) => Instruction::Jp(addr(*nnn, 0)?),
+            ("JP", [V(0), Value(nnn)]) => Instruction::JpV0(addr(*nnn, 1)?),
+            ("CALL", [Value(nnn)]) => Instruction::Call(addr(*nnn, 0)?),
+            ("SE", [V(x), Value(v)]) => Instruction::SeImm {
+                x: *x,
+                nn: nn(*v, 1)?,
+            },
+            ("SE", [V(x), V(y)]) => Instruction::Se { x: *x, y: *y },
+            ("SNE", [V(x), Value(v)]) => Instruction::SneImm {
+                x: *x,
+                nn: nn(*v, 1)?,
+            },
+            ("SNE", [V(x), V(y)]) => Instruction::Sne { x: *x, y: *y },
+            ("LD", [V(x), Value(v)]) => Instruction::LdImm {
+                x: *x,
+                nn: nn(*v, 1)?,
+            },
+            ("LD", [V(x), V(y)]) => Instruction::Ld { x: *x, y: *y },
+            ("LD", [I, Value(nnn)]) => Instruction::LdI(addr(*nnn, 1)?),
+            ("LD", [V(x), Dt]) => Instruction::LdVxDt(*x),
+            ("LD", [V(x), K]) => Instruction::LdVxK(*x),
+            ("LD", [Dt, V(x)]) => Instruction::LdDtVx(*x),
+            ("LD", [St, V(x)]) => Instruction::LdStVx(*x),
+            ("LD", [F, V(x)]) => Instruction::LdF(*x),
+            ("LD", [B, V(x)]) => Instruction::LdB(*x),
+            ("LD", [IndirectI, V(x)]) => Instruction::Store(*x),
+            ("LD", [V(x), IndirectI]) => Instruction::Load(*x),
+            ("ADD", [V(x), Value(v)]) => Instruction::AddImm {
+                x: *x,
+                nn: nn(*v, 1)?,
+            },
+            ("ADD", [V(x), V(y)]) => Instruction::Add { x: *x, y: *y },
+            ("ADD", [I, V(x)]) => Instruction::AddI(*x),
+            ("OR", [V(x), V(y)]) => Instruction::Or { x: *x, y: *y },
+            ("AND", [V(x), V(y)]) => Instruction::And { x: *x, y: *y },
+            ("XOR", [V(x), V(y)]) => Instruction::Xor { x: *x, y: *y },
+            ("SUB", [V(x), V(y)]) => Instruction::Sub { x: *x, y: *y },
+            ("SUBN", [V(x), V(y)]) => Instruction::Subn { x: *x, y: *y },
+            // the second register only matters with the shift_vy quirk
+            ("SHR", [V(x)]) => Instruction::Shr { x: *x, y: *x },
+            ("SHR", [V(x), V(y)]) => Instruction::Shr { x: *x, y: *y },
+            ("SHL", [V(x)]) => Instruction::Shl { x: *x, y: *x },
+            ("SHL", [V(x), V(y)]) => Instruction::Shl { x: *x, y: *y },
+            ("RND", [V(x), Value(v)]) => Instruction::Rnd {
+                x: *x,
+                nn: nn(*v, 1)?,
+            },
+            ("DRW", [V(x), V(y), Value(n)]) => {
+                if *n > 0xF {
+                    return Err(self.operands[2].error("sprites are at most 15 rows"));
+                }
+                Instruction::Drw {
+                    x: *x,
+                    y: *y,
+                    n: *n as u8,
+                }
+            }
+            ("SKP", [V(x)]) => Instruction::Skp(*x),
+            ("SKNP", [V(x)]) => Instruction::Sknp(*x),
+            (
+                "NOP" | "CLS" | "RET" | "LOW" | "HIGH" | "JP" | "CALL" | "SE" | "SNE" | "LD"
+                | "ADD" | "OR" | "AND" | "XOR" | "SUB" | "SUBN" | "SHR" | "SHL" | "RND" | "DRW"
+                | "SKP" | "SKNP",
+                _,
+            ) => {
+                return Err(self
+                    .mnemonic
+                    .error(format!("wrong operands for {}", mnemonic)))
+            }
+            _ => {
+                return Err(self
+                    .mnemonic
+                    .error(format!("unknown instruction {}", self.mnemonic.text)))
+            }
+        };
+        Ok(instruction)
+    }
+}
+
+fn operand(token: &Token, labels: &HashMap<String, u16>) -> Result<Operand, AsmError> {
+    let upper = token.text.to_ascii_uppercase();
+    let operand = match upper.as_str() {
+        "I" => Operand::I,
+        "[I]" => Operand::IndirectI,
+        "DT" => Operand::Dt,
+        "ST" => Operand::St,
+        "K" => Operand::K,
+        "F" => Operand::F,
+        "B" => Operand::B,
+        _ => match upper
+            .strip_prefix('V')
+            .filter(|x| x.len() == 1)
+            .and_then(|x| u8::from_str_radix(x, 16).ok())
+        {
+            Some(x) => Operand::V(x),
+            None => Operand::Value(value(token, labels)?),
+        },
+    };
+    Ok(operand)
+}
+
+fn value(token: &Token, labels: &HashMap<String, u16>) -> Result<u16, AsmError> {
+    if let Some(&addr) = labels.get(token.text) {
+        return Ok(addr);
+    }
+    parse_number(token.text).ok_or_else(|| {
+        if token.text.is_empty() {
+            token.error("missing operand")
+        } else {
+            token.error(format!("{} isn't a number or a label", token.text))
+        }
+    })
+}
+
+fn byte(token: &Token, value: u16) -> Result<u8, AsmError> {
+    u8::try_from(value).map_err(|_| token.error(format!("{} doesn't fit in a byte", token.text)))
+}
+
+pub fn parse_number(s: &str) -> Option<u16> {
+    let lower = s.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('#')) {
+        u16::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = lower.strip_prefix("0b") {
+        u16::from_str_radix(binary, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+
+    fn rom(source: &str) -> Vec<u8> {
+        assemble(source).expect("assembles").rom
+    }
+
+    fn error(source: &str) -> String {
+        assemble(source).expect_err("doesn't assemble").to_string()
+    }
+
+    #[test]
+    fn every_mnemonic() {
+        let source = "
+            NOP
+            CLS
+            RET
+            LOW
+            HIGH
+            JP 0x234
+            JP V0, 0x234
+            CALL 0x234
+            SE V1, 0x22
+            SE V1, V2
+            SNE V1, 0x22
+            SNE V1, V2
+            LD V1, 0x22
+            LD V1, V2
+            LD I, 0x234
+            LD V1, DT
+            LD V1, K
+            LD DT, V1
+            LD ST, V1
+            LD F, V1
+            LD B, V1
+            LD [I], V1
+            LD V1, [I]
+            ADD V1, 0x22
+            ADD V1, V2
+            ADD I, V1
+            OR V1, V2
+            AND V1, V2
+            XOR V1, V2
+            SUB V1, V2
+            SUBN V1, V2
+            SHR V1, V2
+            SHR V1
+            SHL V1, V2
+            SHL V1
+            RND V1, 0x22
+            DRW V1, V2, 3
+            SKP V1
+            SKNP V1
+        ";
+        let words: Vec<u16> = rom(source)
+            .chunks(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(
+            words,
+            [
+                0x0000, 0x00E0, 0x00EE, 0x00FE, 0x00FF, 0x1234, 0xB234, 0x2234, 0x3122, 0x5120,
+                0x4122, 0x9120, 0x6122, 0x8120, 0xA234, 0xF107, 0xF10A, 0xF115, 0xF118, 0xF129,
+                0xF133, 0xF155, 0xF165, 0x7122, 0x8124, 0xF11E, 0x8121, 0x8122, 0x8123, 0x8125,
+                0x8127, 0x8126, 0x8116, 0x812E, 0x811E, 0xC122, 0xD123, 0xE19E, 0xE1A1,
+            ]
+        );
+    }
+
+    #[test]
+    fn disasm_round_trips_every_opcode() {
+        // as many opcodes at a time as fit after DEFAULT_START_ADDR
+        let ops: Vec<u8> = (0..=u16::MAX).flat_map(u16::to_be_bytes).collect();
+        for chunk in ops.chunks(0x8000) {
+            let source: Vec<String> = disassemble(chunk, 0, chunk.len() / 2)
+                .iter()
+                .map(|line| line.source())
+                .collect();
+            assert_eq!(rom(&source.join("\n")), chunk);
+        }
+    }
+
+    #[test]
+    fn labels_and_data() {
+        let assembled = assemble(
+            "start:  LD I, sprite ; forward reference
+                     JP start
+             sprite: DB 0b11000011, #3C
+                     DW 0x1234
+             end:",
+        )
+        .expect("assembles");
+        assert_eq!(
+            assembled.rom,
+            [0xA2, 0x04, 0x12, 0x00, 0xC3, 0x3C, 0x12, 0x34]
+        );
+        assert_eq!(assembled.labels["sprite"], 0x204);
+        assert_eq!(assembled.labels["end"], 0x208);
+        // odd sized data leaves the next instruction unaligned, which is allowed
+        assert_eq!(rom("one: two: DB 1\nJP two"), [0x01, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("0x2A"), Some(42));
+        assert_eq!(parse_number("#2a"), Some(42));
+        assert_eq!(parse_number("0b101010"), Some(42));
+        assert_eq!(parse_number("0x10000"), None);
+        assert_eq!(parse_number("forty"), None);
+    }
+
+    #[test]
+    fn errors_point_at_the_token() {
+        assert_eq!(error("LD V0, 256"), "1:8: 256 doesn't fit in a byte");
+        assert_eq!(error("\n  JP 0x1000"), "2:6: 0x1000 is past 0xFFF");
+        assert_eq!(error("DRW V0, V1, 16"), "1:13: sprites are at most 15 rows");
+        assert_eq!(error("LD DT, 5"), "1:1: wrong operands for LD");
+        assert_eq!(error("  MOV V0, V1"), "1:3: unknown instruction MOV");
+        assert_eq!(
+            error("JP nowhere"),
+            "1:4: nowhere isn't a number or a label"
+        );
+        assert_eq!(error("a: NOP\na: NOP"), "2:1: a is already defined");
+        assert_eq!(error("SE V0,"), "1:7: missing operand");
+    }
+
+    #[test]
+    fn too_big_for_memory() {
+        let fits = "NOP\n".repeat((0x10000 - DEFAULT_START_ADDR as usize) / 2);
+        assert_eq!(rom(&fits).len(), 0x10000 - DEFAULT_START_ADDR as usize);
+        assert_eq!(
+            error(&(fits + "NOP")),
+            format!("{}:1: the rom doesn't fit in memory", 0x7F01)
+        );
+    }
+}
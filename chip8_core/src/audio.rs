@@ -0,0 +1,56 @@
+// square wave generator for the buzzer, so every frontend and recorder sounds the same
+
+pub const DEFAULT_SAMPLE_RATE: u32 = 44100;
+pub const DEFAULT_FREQUENCY: f32 = 440.0;
+// as a fraction of full scale, square waves are loud
+pub const DEFAULT_VOLUME: f32 = 0.25;
+// the timers run at 60Hz so that's the rate samples are asked for
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+pub struct Buzzer {
+    pub frequency: f32,
+    pub volume: f32,
+    sample_rate: u32,
+    phase: f32,
+}
+
+impl Buzzer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            frequency: DEFAULT_FREQUENCY,
+            volume: DEFAULT_VOLUME,
+            sample_rate,
+            phase: 0.0,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn samples_per_frame(&self) -> usize {
+        (self.sample_rate / FRAMES_PER_SECOND) as usize
+    }
+
+    // fills out with the buzzer's output, silence when it isn't active. The phase carries on
+    // between calls so consecutive buffers join up without clicks
+    pub fn fill(&mut self, active: bool, out: &mut [f32]) {
+        let step = self.frequency / self.sample_rate as f32;
+        for sample in out.iter_mut() {
+            *sample = if !active {
+                0.0
+            } else if self.phase < 0.5 {
+                self.volume
+            } else {
+                -self.volume
+            };
+            self.phase = (self.phase + step) % 1.0;
+        }
+    }
+}
+
+impl Default for Buzzer {
+    fn default() -> Self {
+        Self::new(DEFAULT_SAMPLE_RATE)
+    }
+}
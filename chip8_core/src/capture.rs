@@ -0,0 +1,321 @@
+// screenshots and recordings of the display and buzzer
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Seek, SeekFrom, Write};
+
+use crate::audio::FRAMES_PER_SECOND;
+use crate::render::{Palette, Renderer, NUM_COLORS};
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Gif(gif::EncodingError),
+    // a frame that isn't the size the recording was started with
+    FrameSize {
+        len: usize,
+        width: usize,
+        height: usize,
+    },
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(e) => write!(f, "{}", e),
+            CaptureError::Png(e) => write!(f, "png: {}", e),
+            CaptureError::Gif(e) => write!(f, "gif: {}", e),
+            CaptureError::FrameSize { len, width, height } => write!(
+                f,
+                "frame has {} pixels but the recording is {}x{}",
+                len, width, height
+            ),
+        }
+    }
+}
+
+impl Error for CaptureError {}
+
+impl From<io::Error> for CaptureError {
+    fn from(e: io::Error) -> Self {
+        CaptureError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for CaptureError {
+    fn from(e: png::EncodingError) -> Self {
+        CaptureError::Png(e)
+    }
+}
+
+impl From<gif::EncodingError> for CaptureError {
+    fn from(e: gif::EncodingError) -> Self {
+        CaptureError::Gif(e)
+    }
+}
+
+// writes whatever the renderer drew last, so palette, scale and effects carry over
+pub fn write_png<W: Write>(out: W, renderer: &Renderer) -> Result<(), CaptureError> {
+    let mut encoder = png::Encoder::new(out, renderer.width() as u32, renderer.height() as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(renderer.buffer())?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+// one rect per lit pixel on top of a background rect, scaled to whatever size it's shown at
+pub fn write_svg<W, P>(
+    mut out: W,
+    pixels: &[P],
+    width: usize,
+    height: usize,
+    palette: &Palette,
+) -> io::Result<()>
+where
+    W: Write,
+    P: Copy + Into<usize>,
+{
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {w} {h}" width="{sw}" height="{sh}" shape-rendering="crispEdges">"#,
+        w = width,
+        h = height,
+        sw = width * 10,
+        sh = height * 10
+    )?;
+    writeln!(
+        out,
+        r#"<rect width="{}" height="{}" fill="{}"/>"#,
+        width,
+        height,
+        hex_color(palette.colors[0])
+    )?;
+    for (idx, &pixel) in pixels.iter().enumerate() {
+        let color_idx = pixel.into() % NUM_COLORS;
+        if color_idx != 0 {
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="1" height="1" fill="{}"/>"#,
+                idx % width,
+                idx / width,
+                hex_color(palette.colors[color_idx])
+            )?;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+fn hex_color(color: [u8; 4]) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+// animated gif made from one frame per 60Hz tick. Identical frames are merged into a longer
+// delay which keeps recordings of mostly static games small
+pub struct GifRecorder<W: Write> {
+    encoder: gif::Encoder<W>,
+    width: usize,
+    height: usize,
+    scale: usize,
+    pending: Option<Vec<u8>>,
+    // delay of the pending frame in 1/60ths of a second
+    pending_frames: u32,
+    // gif delays are in centiseconds which 60fps doesn't divide, so the rounding is carried over
+    leftover_cs: f32,
+}
+
+impl<W: Write> GifRecorder<W> {
+    pub fn new(
+        out: W,
+        width: usize,
+        height: usize,
+        scale: usize,
+        palette: &Palette,
+    ) -> Result<Self, CaptureError> {
+        let scale = scale.max(1);
+        let global_palette: Vec<u8> = palette
+            .colors
+            .iter()
+            .flat_map(|c| [c[0], c[1], c[2]])
+            .collect();
+        let mut encoder = gif::Encoder::new(
+            out,
+            (width * scale) as u16,
+            (height * scale) as u16,
+            &global_palette,
+        )?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            scale,
+            pending: None,
+            pending_frames: 0,
+            leftover_cs: 0.0,
+        })
+    }
+
+    pub fn add_frame<P: Copy + Into<usize>>(&mut self, pixels: &[P]) -> Result<(), CaptureError> {
+        if pixels.len() != self.width * self.height {
+            return Err(CaptureError::FrameSize {
+                len: pixels.len(),
+                width: self.width,
+                height: self.height,
+            });
+        }
+        let out_width = self.width * self.scale;
+        let mut indices = vec![0; out_width * self.height * self.scale];
+        for (idx, &pixel) in pixels.iter().enumerate() {
+            let color_idx = (pixel.into() % NUM_COLORS) as u8;
+            let x = (idx % self.width) * self.scale;
+            let y = (idx / self.width) * self.scale;
+            for dy in 0..self.scale {
+                let row = (y + dy) * out_width + x;
+                indices[row..row + self.scale].fill(color_idx);
+            }
+        }
+
+        if self.pending.as_ref() == Some(&indices) {
+            self.pending_frames += 1;
+            return Ok(());
+        }
+        self.flush()?;
+        self.pending = Some(indices);
+        self.pending_frames = 1;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, CaptureError> {
+        self.flush()?;
+        Ok(self.encoder.into_inner()?)
+    }
+
+    fn flush(&mut self) -> Result<(), CaptureError> {
+        let indices = match self.pending.take() {
+            Some(indices) => indices,
+            None => return Ok(()),
+        };
+
+        let exact =
+            self.pending_frames as f32 * 100.0 / FRAMES_PER_SECOND as f32 + self.leftover_cs;
+        let delay = exact.round().max(1.0);
+        self.leftover_cs = exact - delay;
+
+        let mut frame = gif::Frame::from_indexed_pixels(
+            (self.width * self.scale) as u16,
+            (self.height * self.scale) as u16,
+            indices,
+            None,
+        );
+        frame.delay = delay as u16;
+        self.encoder.write_frame(&frame)?;
+
+        Ok(())
+    }
+}
+
+// raw YUV4MPEG2 video at 60fps, which ffmpeg and most players read directly. Frames are taken
+// from the renderer so the video matches what's on screen
+pub struct Y4mWriter<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut out: W, width: usize, height: usize) -> io::Result<Self> {
+        writeln!(
+            out,
+            "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+            width, height, FRAMES_PER_SECOND
+        )?;
+        Ok(Self { out, width, height })
+    }
+
+    pub fn add_frame(&mut self, renderer: &Renderer) -> io::Result<()> {
+        if renderer.width() != self.width || renderer.height() != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frame size changed during recording",
+            ));
+        }
+
+        let rgba = renderer.buffer();
+        let len = self.width * self.height;
+        let mut planes = vec![0; len * 3];
+        for idx in 0..len {
+            let (r, g, b) = (
+                rgba[idx * 4] as f32,
+                rgba[idx * 4 + 1] as f32,
+                rgba[idx * 4 + 2] as f32,
+            );
+            // BT.601 studio range
+            planes[idx] = (16.0 + 0.257 * r + 0.504 * g + 0.098 * b) as u8;
+            planes[len + idx] = (128.0 - 0.148 * r - 0.291 * g + 0.439 * b) as u8;
+            planes[len * 2 + idx] = (128.0 + 0.439 * r - 0.368 * g - 0.071 * b) as u8;
+        }
+
+        self.out.write_all(b"FRAME\n")?;
+        self.out.write_all(&planes)
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+// 16 bit mono pcm. The header is written with empty sizes and patched in finish, which is why
+// this needs a seekable writer
+pub struct WavWriter<W: Write + Seek> {
+    out: W,
+    samples: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut out: W, sample_rate: u32) -> io::Result<Self> {
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        // pcm, one channel
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&sample_rate.to_le_bytes())?;
+        out.write_all(&(sample_rate * 2).to_le_bytes())?;
+        out.write_all(&2u16.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+
+        Ok(Self { out, samples: 0 })
+    }
+
+    pub fn add_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+        for &sample in samples {
+            let pcm = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.out.write_all(&pcm.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u32;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let data_len = self.samples * 2;
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(36 + data_len).to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(40))?;
+        self.out.write_all(&data_len.to_le_bytes())?;
+        self.out.seek(SeekFrom::End(0))?;
+        self.out.flush()?;
+
+        Ok(self.out)
+    }
+}
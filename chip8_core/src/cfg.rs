@@ -0,0 +1,538 @@
+// static control flow recovery. Starting from DEFAULT_START_ADDR it follows jumps, calls, skips
+// and returns to find which bytes of a rom are code, splits the code into basic blocks and
+// groups the blocks into subroutines. Anything never reached is taken to be data.
+//
+// Nothing is run, so a BNNN jump goes nowhere as far as this is concerned and is flagged
+// instead, along with stores that land on code. I is only tracked within a block, from the last
+// LD I, NNN
+
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+use crate::config::MAX_RAM_SIZE;
+use crate::disasm::{Instruction, Line};
+use crate::{LoadError, DEFAULT_START_ADDR};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    // on to the next instruction
+    Next,
+    Jump,
+    // the instruction after the next, when a skip's condition holds
+    Skip,
+    // into a subroutine, which comes back to the block after the call
+    Call,
+}
+
+impl EdgeKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Next => "next",
+            EdgeKind::Jump => "jump",
+            EdgeKind::Skip => "skip",
+            EdgeKind::Call => "call",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub kind: EdgeKind,
+    pub to: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub start: u16,
+    // the block's last byte, so one at the very top of memory still fits in a u16
+    pub last: u16,
+    pub lines: Vec<Line>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subroutine {
+    pub entry: u16,
+    // the starts of the blocks reachable from entry without going through a call
+    pub blocks: Vec<u16>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Warning {
+    // BNNN at addr, which can't be followed
+    ComputedJump { addr: u16 },
+    // a store at addr writes to code at target
+    SelfModifying { addr: u16, target: u16 },
+    // a jump, call or skip at addr goes outside the rom, maybe past the top of memory
+    OutOfRom { addr: u16, target: usize },
+    // an opcode the emulator doesn't know, where the flow stops
+    Unknown { addr: u16, op: u16 },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Warning::ComputedJump { addr } => {
+                write!(
+                    f,
+                    "{:03X}: computed jump, its targets aren't followed",
+                    addr
+                )
+            }
+            Warning::SelfModifying { addr, target } => {
+                write!(f, "{:03X}: writes to code at {:03X}", addr, target)
+            }
+            Warning::OutOfRom { addr, target } => {
+                write!(f, "{:03X}: goes to {:03X}, outside the rom", addr, target)
+            }
+            Warning::Unknown { addr, op } => write!(f, "{:03X}: unknown opcode {:04X}", addr, op),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cfg {
+    pub entry: u16,
+    // keyed by start address
+    pub blocks: BTreeMap<u16, Block>,
+    // the entry point first, then every call target in address order
+    pub subroutines: Vec<Subroutine>,
+    // byte ranges of the rom that are never reached, first and last byte
+    pub data: Vec<(u16, u16)>,
+    pub warnings: Vec<Warning>,
+}
+
+// roms have to fit in 64K after DEFAULT_START_ADDR, the most any address can reach
+pub fn analyze(rom: &[u8]) -> Result<Cfg, LoadError> {
+    let start = DEFAULT_START_ADDR as usize;
+    let capacity = MAX_RAM_SIZE - start;
+    if rom.len() > capacity {
+        return Err(LoadError::TooLarge {
+            size: rom.len(),
+            capacity,
+        });
+    }
+    // addresses stay usize until they go into the Cfg, since the instruction after one at the
+    // top of memory is past u16
+    let end = start + rom.len();
+    let mut ram = vec![0; start];
+    ram.extend_from_slice(rom);
+    let in_rom = |addr: usize| addr >= start && addr + 1 < end;
+    let decode = |addr: usize| {
+        let op = u16::from_be_bytes([ram[addr], ram[addr + 1]]);
+        Line {
+            addr: addr as u16,
+            op,
+            instruction: Instruction::decode(op),
+        }
+    };
+
+    // first find every instruction that can be reached and where blocks have to start
+    let mut code: BTreeMap<usize, Line> = BTreeMap::new();
+    let mut leaders = BTreeSet::from([start]);
+    let mut calls = BTreeSet::new();
+    let mut warnings = Vec::new();
+    let mut pending = vec![start];
+    while let Some(addr) = pending.pop() {
+        if !in_rom(addr) || code.contains_key(&addr) {
+            continue;
+        }
+        let line = decode(addr);
+        code.insert(addr, line.clone());
+        let mut targets = Vec::new();
+        match line.instruction {
+            Instruction::Jp(nnn) => {
+                leaders.insert(nnn as usize);
+                targets.push(nnn as usize);
+            }
+            Instruction::Call(nnn) => {
+                calls.insert(nnn as usize);
+                leaders.extend([nnn as usize, addr + 2]);
+                targets.extend([nnn as usize, addr + 2]);
+            }
+            Instruction::JpV0(_) => warnings.push(Warning::ComputedJump { addr: line.addr }),
+            Instruction::Ret => {}
+            Instruction::Unknown(op) => warnings.push(Warning::Unknown {
+                addr: line.addr,
+                op,
+            }),
+            ref i if i.is_skip() => {
+                leaders.extend([addr + 2, addr + 4]);
+                targets.extend([addr + 2, addr + 4]);
+            }
+            _ => targets.push(addr + 2),
+        }
+        for target in targets {
+            if !in_rom(target) {
+                warnings.push(Warning::OutOfRom {
+                    addr: line.addr,
+                    target,
+                });
+            }
+            pending.push(target);
+        }
+    }
+
+    // then cut the code into blocks. A block also ends where the next instruction isn't code,
+    // which happens when one overlaps another at an odd address
+    let mut blocks = BTreeMap::new();
+    let mut current: Option<(usize, Vec<Line>)> = None;
+    for (&addr, line) in &code {
+        let continues = current.as_ref().is_some_and(|(_, lines)| {
+            lines
+                .last()
+                .is_some_and(|last| last.addr as usize + 2 == addr)
+                && !leaders.contains(&addr)
+        });
+        if !continues {
+            if let Some((block_start, lines)) = current.take() {
+                blocks.insert(block_start as u16, block(block_start, lines, Vec::new()));
+            }
+            current = Some((addr, Vec::new()));
+        }
+        let (block_start, lines) = current.as_mut().expect("a block was just started");
+        lines.push(line.clone());
+        let next = addr + 2;
+        let edges = match line.instruction {
+            Instruction::Jp(nnn) => vec![(EdgeKind::Jump, nnn as usize)],
+            Instruction::Call(nnn) => vec![(EdgeKind::Call, nnn as usize), (EdgeKind::Next, next)],
+            Instruction::JpV0(_) | Instruction::Ret | Instruction::Unknown(_) => vec![],
+            ref i if i.is_skip() => vec![(EdgeKind::Next, next), (EdgeKind::Skip, addr + 4)],
+            _ if leaders.contains(&next) || !code.contains_key(&next) => {
+                vec![(EdgeKind::Next, next)]
+            }
+            _ => continue,
+        };
+        let edges = edges
+            .into_iter()
+            .filter(|(_, to)| code.contains_key(to))
+            .map(|(kind, to)| Edge {
+                kind,
+                to: to as u16,
+            })
+            .collect();
+        let block_start = *block_start;
+        let (_, lines) = current.take().expect("a block is open");
+        blocks.insert(block_start as u16, block(block_start, lines, edges));
+    }
+    if let Some((block_start, lines)) = current {
+        blocks.insert(block_start as u16, block(block_start, lines, Vec::new()));
+    }
+
+    let subroutines = std::iter::once(start)
+        .chain(calls.into_iter().filter(|&c| c != start))
+        .filter(|&entry| code.contains_key(&entry))
+        .map(|entry| subroutine(&blocks, entry as u16))
+        .collect();
+
+    warnings.extend(self_modifying(&blocks, &code));
+
+    // runs of bytes no instruction covers
+    let mut data = Vec::new();
+    let mut run_start = None;
+    for addr in start..=end {
+        let covered = code.contains_key(&addr) || (addr > start && code.contains_key(&(addr - 1)));
+        match (covered || addr == end, run_start) {
+            (true, Some(first)) => {
+                data.push((first as u16, (addr - 1) as u16));
+                run_start = None;
+            }
+            (false, None) => run_start = Some(addr),
+            _ => {}
+        }
+    }
+
+    warnings.sort_by_key(|w| match *w {
+        Warning::ComputedJump { addr }
+        | Warning::SelfModifying { addr, .. }
+        | Warning::OutOfRom { addr, .. }
+        | Warning::Unknown { addr, .. } => addr,
+    });
+    warnings.dedup();
+    Ok(Cfg {
+        entry: DEFAULT_START_ADDR,
+        blocks,
+        subroutines,
+        data,
+        warnings,
+    })
+}
+
+fn block(start: usize, lines: Vec<Line>, edges: Vec<Edge>) -> Block {
+    let last = lines.last().map_or(start, |line| line.addr as usize + 1);
+    Block {
+        start: start as u16,
+        last: last as u16,
+        lines,
+        edges,
+    }
+}
+
+fn subroutine(blocks: &BTreeMap<u16, Block>, entry: u16) -> Subroutine {
+    let mut seen = BTreeSet::new();
+    let mut pending = vec![entry];
+    while let Some(start) = pending.pop() {
+        if !seen.insert(start) {
+            continue;
+        }
+        if let Some(block) = blocks.get(&start) {
+            pending.extend(
+                block
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.kind != EdgeKind::Call)
+                    .map(|edge| edge.to),
+            );
+        }
+    }
+    Subroutine {
+        entry,
+        blocks: seen.into_iter().collect(),
+    }
+}
+
+// stores and BCDs whose I is known from earlier in the same block and lands on code
+fn self_modifying(blocks: &BTreeMap<u16, Block>, code: &BTreeMap<usize, Line>) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    for block in blocks.values() {
+        let mut i = None;
+        for line in &block.lines {
+            let written = match line.instruction {
+                Instruction::LdI(nnn) => {
+                    i = Some(nnn as usize);
+                    continue;
+                }
+                Instruction::AddI(_) | Instruction::LdF(_) | Instruction::Load(_) => {
+                    i = None;
+                    continue;
+                }
+                Instruction::Store(x) => x as usize + 1,
+                Instruction::LdB(_) => 3,
+                _ => continue,
+            };
+            let Some(i) = i else { continue };
+            // an instruction starting the byte before I still has its second byte overwritten
+            let hit = (i.saturating_sub(1)..i + written).find(|addr| code.contains_key(addr));
+            if let Some(target) = hit {
+                warnings.push(Warning::SelfModifying {
+                    addr: line.addr,
+                    target: target.max(i) as u16,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+impl Cfg {
+    pub fn is_code(&self, addr: u16) -> bool {
+        self.blocks
+            .range(..=addr)
+            .next_back()
+            .is_some_and(|(_, block)| addr <= block.last)
+    }
+
+    // Graphviz, one box per block with the listing inside and one cluster per subroutine
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph cfg {\n    node [shape=box fontname=monospace];\n");
+        let computed: BTreeSet<u16> = self
+            .warnings
+            .iter()
+            .filter_map(|w| match *w {
+                Warning::ComputedJump { addr } => Some(addr),
+                _ => None,
+            })
+            .collect();
+        let mut placed = BTreeSet::new();
+        for sub in &self.subroutines {
+            let _ = writeln!(out, "    subgraph cluster_{:03X} {{", sub.entry);
+            let _ = writeln!(out, "        label=\"sub_{:03X}\";", sub.entry);
+            for start in &sub.blocks {
+                // blocks shared by subroutines are drawn in the first
+                if placed.insert(*start) {
+                    let block = &self.blocks[start];
+                    let flagged = block.lines.iter().any(|l| computed.contains(&l.addr));
+                    let _ = writeln!(out, "        {}", dot_node(block, flagged));
+                }
+            }
+            out.push_str("    }\n");
+        }
+        for block in self.blocks.values().filter(|b| !placed.contains(&b.start)) {
+            let flagged = block.lines.iter().any(|l| computed.contains(&l.addr));
+            let _ = writeln!(out, "    {}", dot_node(block, flagged));
+        }
+        for block in self.blocks.values() {
+            for edge in &block.edges {
+                let style = match edge.kind {
+                    EdgeKind::Next => "",
+                    EdgeKind::Jump => " [color=blue]",
+                    EdgeKind::Skip => " [style=dashed label=skip]",
+                    EdgeKind::Call => " [style=dotted label=call]",
+                };
+                let _ = writeln!(
+                    out,
+                    "    b{:03X} -> b{:03X}{};",
+                    block.start, edge.to, style
+                );
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_json(&self) -> String {
+        let hex = |addr: u16| format!("{:03X}", addr);
+        let value = json!({
+            "entry": hex(self.entry),
+            "blocks": self.blocks.values().map(|block| json!({
+                "start": hex(block.start),
+                "last": hex(block.last),
+                "instructions": block.lines.iter().map(|line| json!({
+                    "addr": hex(line.addr),
+                    "op": format!("{:04X}", line.op),
+                    "text": line.instruction.to_string(),
+                })).collect::<Vec<_>>(),
+                "edges": block.edges.iter().map(|edge| json!({
+                    "kind": edge.kind.name(),
+                    "to": hex(edge.to),
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "subroutines": self.subroutines.iter().map(|sub| json!({
+                "entry": hex(sub.entry),
+                "blocks": sub.blocks.iter().map(|&b| hex(b)).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "data": self.data.iter().map(|&(start, last)| json!({
+                "start": hex(start),
+                "last": hex(last),
+            })).collect::<Vec<_>>(),
+            "warnings": self.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+        });
+        serde_json::to_string_pretty(&value).expect("json values always serialize")
+    }
+}
+
+fn dot_node(block: &Block, flagged: bool) -> String {
+    // \l left-justifies each line
+    let label: String = block
+        .lines
+        .iter()
+        .map(|line| format!("{}\\l", line))
+        .collect();
+    let color = if flagged { " color=red" } else { "" };
+    format!("b{:03X} [label=\"{}\"{}];", block.start, label, color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a rom that fills memory, all NOPs up to the word at the very top
+    fn filling(last: u16) -> Vec<u8> {
+        let mut rom = vec![0; MAX_RAM_SIZE - DEFAULT_START_ADDR as usize];
+        let len = rom.len();
+        rom[len - 2..].copy_from_slice(&last.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    fn call_at_the_top_of_memory() {
+        let cfg = analyze(&filling(0x2202)).expect("fits");
+        assert_eq!(
+            cfg.warnings,
+            [Warning::OutOfRom {
+                addr: 0xFFFE,
+                target: 0x10000
+            }]
+        );
+        assert_eq!(cfg.blocks[&0x202].last, 0xFFFF);
+        assert!(cfg.data.is_empty());
+    }
+
+    #[test]
+    fn skip_at_the_top_of_memory() {
+        let cfg = analyze(&filling(0x3000)).expect("fits");
+        assert_eq!(
+            cfg.warnings,
+            [
+                Warning::OutOfRom {
+                    addr: 0xFFFE,
+                    target: 0x10000
+                },
+                Warning::OutOfRom {
+                    addr: 0xFFFE,
+                    target: 0x10002
+                },
+            ]
+        );
+        assert!(cfg.is_code(0xFFFF));
+    }
+
+    #[test]
+    fn too_large() {
+        let rom = vec![0; MAX_RAM_SIZE - DEFAULT_START_ADDR as usize + 1];
+        assert_eq!(
+            analyze(&rom),
+            Err(LoadError::TooLarge {
+                size: 0xFE01,
+                capacity: 0xFE00
+            })
+        );
+    }
+
+    #[test]
+    fn blocks_subroutines_and_data() {
+        let rom = [
+            0x22, 0x08, // 200: CALL 0x208
+            0x30, 0x01, // 202: SE V0, 0x01
+            0x12, 0x02, // 204: JP 0x202
+            0x12, 0x06, // 206: JP 0x206
+            0xA2, 0x0E, // 208: LD I, 0x20E
+            0xF0, 0x55, // 20A: LD [I], V0
+            0x00, 0xEE, // 20C: RET
+            0xFF, 0xFF, // 20E: data
+        ];
+        let cfg = analyze(&rom).expect("fits");
+        let starts: Vec<u16> = cfg.blocks.keys().copied().collect();
+        assert_eq!(starts, [0x200, 0x202, 0x204, 0x206, 0x208]);
+        assert_eq!(
+            cfg.blocks[&0x202].edges,
+            [
+                Edge {
+                    kind: EdgeKind::Next,
+                    to: 0x204
+                },
+                Edge {
+                    kind: EdgeKind::Skip,
+                    to: 0x206
+                },
+            ]
+        );
+        assert_eq!(cfg.subroutines.len(), 2);
+        assert_eq!(cfg.subroutines[1].entry, 0x208);
+        assert_eq!(cfg.subroutines[1].blocks, [0x208]);
+        assert_eq!(cfg.data, [(0x20E, 0x20F)]);
+        // the store lands on the data, not on code
+        assert!(cfg.warnings.is_empty());
+    }
+
+    #[test]
+    fn flags_computed_jumps_and_writes_to_code() {
+        let rom = [
+            0xA2, 0x04, // 200: LD I, 0x204
+            0xF1, 0x55, // 202: LD [I], V1
+            0xB2, 0x00, // 204: JP V0, 0x200
+        ];
+        let cfg = analyze(&rom).expect("fits");
+        assert_eq!(
+            cfg.warnings,
+            [
+                Warning::SelfModifying {
+                    addr: 0x202,
+                    target: 0x204
+                },
+                Warning::ComputedJump { addr: 0x204 },
+            ]
+        );
+    }
+}
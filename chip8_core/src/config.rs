@@ -0,0 +1,101 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::FONTSET_SIZE;
+
+pub const DEFAULT_RAM_SIZE: usize = 4096;
+pub const DEFAULT_START_ADDR: u16 = 0x200;
+pub const DEFAULT_STACK_DEPTH: usize = 16;
+
+// addresses are 12 bits in the instruction set but I and pc are 16 bits wide, so that's the most
+// memory an interpreter can reach
+pub(crate) const MAX_RAM_SIZE: usize = 0x10000;
+
+// layout of the machine an Emu runs on, checked once when the emulator is created
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MachineConfig {
+    pub ram_size: usize,
+    pub start_addr: u16,
+    pub stack_depth: usize,
+}
+
+impl MachineConfig {
+    // ETI-660 programs load at 0x600
+    pub fn eti_660() -> Self {
+        Self {
+            start_addr: 0x600,
+            ..Self::default()
+        }
+    }
+
+    // hybrid programs that carry their own machine code expect to start at 0x000
+    pub fn hybrid() -> Self {
+        Self {
+            start_addr: 0x000,
+            ..Self::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.ram_size < FONTSET_SIZE || self.ram_size > MAX_RAM_SIZE {
+            return Err(ConfigError::RamSize(self.ram_size));
+        }
+        // there has to be room for at least one instruction after the start address
+        if self.start_addr as usize + 2 > self.ram_size {
+            return Err(ConfigError::StartAddr {
+                start_addr: self.start_addr,
+                ram_size: self.ram_size,
+            });
+        }
+        if self.stack_depth == 0 || self.stack_depth > u16::MAX as usize {
+            return Err(ConfigError::StackDepth(self.stack_depth));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        Self {
+            ram_size: DEFAULT_RAM_SIZE,
+            start_addr: DEFAULT_START_ADDR,
+            stack_depth: DEFAULT_STACK_DEPTH,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigError {
+    RamSize(usize),
+    StartAddr { start_addr: u16, ram_size: usize },
+    StackDepth(usize),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::RamSize(size) => write!(
+                f,
+                "ram size {} must be between {} and {} bytes",
+                size, FONTSET_SIZE, MAX_RAM_SIZE
+            ),
+            ConfigError::StartAddr {
+                start_addr,
+                ram_size,
+            } => write!(
+                f,
+                "start address {:#05X} leaves no room for a program in {} bytes of ram",
+                start_addr, ram_size
+            ),
+            ConfigError::StackDepth(depth) => write!(
+                f,
+                "stack depth {} must be between 1 and {}",
+                depth,
+                u16::MAX
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {}
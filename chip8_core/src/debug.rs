@@ -0,0 +1,143 @@
+// breakpoints and watches for debuggers. The Emu doesn't know about any of this, the Debugger
+// drives it one instruction at a time and checks after each one
+
+use std::collections::BTreeSet;
+
+use crate::{Emu, Fault};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watch {
+    Memory(u16),
+    Register(u8),
+    I,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(u16),
+    Watch { watch: Watch, old: u16, new: u16 },
+    Fault(Fault),
+}
+
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    // each watch with the value it had after the last instruction
+    watches: Vec<(Watch, u16)>,
+    // the breakpoint run last stopped at, which mustn't stop it again straight away
+    stopped_at: Option<u16>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    pub fn has_breakpoint(&self, addr: u16) -> bool {
+        self.breakpoints.contains(&addr)
+    }
+
+    pub fn set_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn clear_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.remove(&addr)
+    }
+
+    // returns whether the breakpoint is now set
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        if !self.breakpoints.remove(&addr) {
+            self.breakpoints.insert(addr);
+            return true;
+        }
+        false
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = Watch> + '_ {
+        self.watches.iter().map(|(watch, _)| *watch)
+    }
+
+    pub fn watch(&mut self, emu: &Emu, watch: Watch) {
+        if !self.watches.iter().any(|(w, _)| *w == watch) {
+            self.watches.push((watch, read(emu, watch)));
+        }
+    }
+
+    pub fn unwatch(&mut self, watch: Watch) -> bool {
+        let len = self.watches.len();
+        self.watches.retain(|(w, _)| *w != watch);
+        self.watches.len() != len
+    }
+
+    // the emulator may have been changed from outside, e.g. by loading a state, so the values
+    // watches compare against need to catch up
+    pub fn sync(&mut self, emu: &Emu) {
+        for (watch, value) in self.watches.iter_mut() {
+            *value = read(emu, *watch);
+        }
+    }
+
+    // executes exactly one instruction, ignoring breakpoints
+    pub fn step(&mut self, emu: &mut Emu) -> Option<StopReason> {
+        if let Some(fault) = emu.fault() {
+            return Some(StopReason::Fault(fault));
+        }
+        self.stopped_at = None;
+        emu.tick();
+        if let Some(fault) = emu.fault() {
+            return Some(StopReason::Fault(fault));
+        }
+
+        let mut reason = None;
+        for (watch, value) in self.watches.iter_mut() {
+            let new = read(emu, *watch);
+            if new != *value {
+                reason.get_or_insert(StopReason::Watch {
+                    watch: *watch,
+                    old: *value,
+                    new,
+                });
+                *value = new;
+            }
+        }
+        reason
+    }
+
+    // runs up to ticks instructions, stopping before any instruction with a breakpoint. Running
+    // again after stopping at a breakpoint carries on past it
+    pub fn run(&mut self, emu: &mut Emu, ticks: usize) -> Option<StopReason> {
+        for _ in 0..ticks {
+            let pc = emu.pc();
+            if self.has_breakpoint(pc) && self.stopped_at != Some(pc) {
+                self.stopped_at = Some(pc);
+                return Some(StopReason::Breakpoint(pc));
+            }
+            if let Some(reason) = self.step(emu) {
+                return Some(reason);
+            }
+        }
+        None
+    }
+
+    // unlike Emu::run_frame, which always ticks, the timers only tick if nothing stopped the frame
+    pub fn run_frame(&mut self, emu: &mut Emu, ticks_per_frame: usize) -> Option<StopReason> {
+        let reason = self.run(emu, ticks_per_frame);
+        if reason.is_none() {
+            emu.tick_timers();
+        }
+        reason
+    }
+}
+
+fn read(emu: &Emu, watch: Watch) -> u16 {
+    match watch {
+        Watch::Memory(addr) => emu.ram().get(addr as usize).copied().unwrap_or(0) as u16,
+        Watch::Register(x) => emu.v_regs()[x as usize & 0xF] as u16,
+        Watch::I => emu.i_reg(),
+    }
+}
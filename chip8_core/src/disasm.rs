@@ -0,0 +1,302 @@
+// decoding of opcodes into instructions and their mnemonics, for debuggers and tools. The
+// mnemonics follow Cowgod's reference, e.g. "LD V1, 0x12" and "DRW V0, V1, 5"
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Nop,
+    Cls,
+    Ret,
+    Low,
+    High,
+    Jp(u16),
+    Call(u16),
+    SeImm { x: u8, nn: u8 },
+    SneImm { x: u8, nn: u8 },
+    Se { x: u8, y: u8 },
+    LdImm { x: u8, nn: u8 },
+    AddImm { x: u8, nn: u8 },
+    Ld { x: u8, y: u8 },
+    Or { x: u8, y: u8 },
+    And { x: u8, y: u8 },
+    Xor { x: u8, y: u8 },
+    Add { x: u8, y: u8 },
+    Sub { x: u8, y: u8 },
+    Shr { x: u8, y: u8 },
+    Subn { x: u8, y: u8 },
+    Shl { x: u8, y: u8 },
+    Sne { x: u8, y: u8 },
+    LdI(u16),
+    JpV0(u16),
+    Rnd { x: u8, nn: u8 },
+    Drw { x: u8, y: u8, n: u8 },
+    Skp(u8),
+    Sknp(u8),
+    LdVxDt(u8),
+    LdVxK(u8),
+    LdDtVx(u8),
+    LdStVx(u8),
+    AddI(u8),
+    LdF(u8),
+    LdB(u8),
+    Store(u8),
+    Load(u8),
+    // anything the emulator would fault on
+    Unknown(u16),
+}
+
+impl Instruction {
+    pub fn decode(op: u16) -> Self {
+        let nibbles = (
+            (op & 0xF000) >> 12,
+            (op & 0x0F00) >> 8,
+            (op & 0x00F0) >> 4,
+            op & 0x000F,
+        );
+        let x = nibbles.1 as u8;
+        let y = nibbles.2 as u8;
+        let n = nibbles.3 as u8;
+        let nn = (op & 0xFF) as u8;
+        let nnn = op & 0xFFF;
+
+        match nibbles {
+            (0, 0, 0, 0) => Instruction::Nop,
+            (0, 0, 0xE, 0) => Instruction::Cls,
+            (0, 0, 0xE, 0xE) => Instruction::Ret,
+            (0, 0, 0xF, 0xE) => Instruction::Low,
+            (0, 0, 0xF, 0xF) => Instruction::High,
+            (1, _, _, _) => Instruction::Jp(nnn),
+            (2, _, _, _) => Instruction::Call(nnn),
+            (3, _, _, _) => Instruction::SeImm { x, nn },
+            (4, _, _, _) => Instruction::SneImm { x, nn },
+            (5, _, _, _) => Instruction::Se { x, y },
+            (6, _, _, _) => Instruction::LdImm { x, nn },
+            (7, _, _, _) => Instruction::AddImm { x, nn },
+            (8, _, _, 0) => Instruction::Ld { x, y },
+            (8, _, _, 1) => Instruction::Or { x, y },
+            (8, _, _, 2) => Instruction::And { x, y },
+            (8, _, _, 3) => Instruction::Xor { x, y },
+            (8, _, _, 4) => Instruction::Add { x, y },
+            (8, _, _, 5) => Instruction::Sub { x, y },
+            (8, _, _, 6) => Instruction::Shr { x, y },
+            (8, _, _, 7) => Instruction::Subn { x, y },
+            (8, _, _, 0xE) => Instruction::Shl { x, y },
+            (9, _, _, 0) => Instruction::Sne { x, y },
+            (0xA, _, _, _) => Instruction::LdI(nnn),
+            (0xB, _, _, _) => Instruction::JpV0(nnn),
+            (0xC, _, _, _) => Instruction::Rnd { x, nn },
+            (0xD, _, _, _) => Instruction::Drw { x, y, n },
+            (0xE, _, 9, 0xE) => Instruction::Skp(x),
+            (0xE, _, 0xA, 1) => Instruction::Sknp(x),
+            (0xF, _, 0, 7) => Instruction::LdVxDt(x),
+            (0xF, _, 0, 0xA) => Instruction::LdVxK(x),
+            (0xF, _, 1, 5) => Instruction::LdDtVx(x),
+            (0xF, _, 1, 8) => Instruction::LdStVx(x),
+            (0xF, _, 1, 0xE) => Instruction::AddI(x),
+            (0xF, _, 2, 9) => Instruction::LdF(x),
+            (0xF, _, 3, 3) => Instruction::LdB(x),
+            (0xF, _, 5, 5) => Instruction::Store(x),
+            (0xF, _, 6, 5) => Instruction::Load(x),
+            (_, _, _, _) => Instruction::Unknown(op),
+        }
+    }
+
+    // the opcode decode() would turn back into this instruction
+    pub fn encode(&self) -> u16 {
+        fn xy(base: u16, x: u8, y: u8) -> u16 {
+            base | (x as u16 & 0xF) << 8 | (y as u16 & 0xF) << 4
+        }
+        fn xnn(base: u16, x: u8, nn: u8) -> u16 {
+            base | (x as u16 & 0xF) << 8 | nn as u16
+        }
+        match *self {
+            Instruction::Nop => 0x0000,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Low => 0x00FE,
+            Instruction::High => 0x00FF,
+            Instruction::Jp(nnn) => 0x1000 | nnn & 0xFFF,
+            Instruction::Call(nnn) => 0x2000 | nnn & 0xFFF,
+            Instruction::SeImm { x, nn } => xnn(0x3000, x, nn),
+            Instruction::SneImm { x, nn } => xnn(0x4000, x, nn),
+            Instruction::Se { x, y } => xy(0x5000, x, y),
+            Instruction::LdImm { x, nn } => xnn(0x6000, x, nn),
+            Instruction::AddImm { x, nn } => xnn(0x7000, x, nn),
+            Instruction::Ld { x, y } => xy(0x8000, x, y),
+            Instruction::Or { x, y } => xy(0x8001, x, y),
+            Instruction::And { x, y } => xy(0x8002, x, y),
+            Instruction::Xor { x, y } => xy(0x8003, x, y),
+            Instruction::Add { x, y } => xy(0x8004, x, y),
+            Instruction::Sub { x, y } => xy(0x8005, x, y),
+            Instruction::Shr { x, y } => xy(0x8006, x, y),
+            Instruction::Subn { x, y } => xy(0x8007, x, y),
+            Instruction::Shl { x, y } => xy(0x800E, x, y),
+            Instruction::Sne { x, y } => xy(0x9000, x, y),
+            Instruction::LdI(nnn) => 0xA000 | nnn & 0xFFF,
+            Instruction::JpV0(nnn) => 0xB000 | nnn & 0xFFF,
+            Instruction::Rnd { x, nn } => xnn(0xC000, x, nn),
+            Instruction::Drw { x, y, n } => xy(0xD000, x, y) | n as u16 & 0xF,
+            Instruction::Skp(x) => xnn(0xE09E, x, 0),
+            Instruction::Sknp(x) => xnn(0xE0A1, x, 0),
+            Instruction::LdVxDt(x) => xnn(0xF007, x, 0),
+            Instruction::LdVxK(x) => xnn(0xF00A, x, 0),
+            Instruction::LdDtVx(x) => xnn(0xF015, x, 0),
+            Instruction::LdStVx(x) => xnn(0xF018, x, 0),
+            Instruction::AddI(x) => xnn(0xF01E, x, 0),
+            Instruction::LdF(x) => xnn(0xF029, x, 0),
+            Instruction::LdB(x) => xnn(0xF033, x, 0),
+            Instruction::Store(x) => xnn(0xF055, x, 0),
+            Instruction::Load(x) => xnn(0xF065, x, 0),
+            Instruction::Unknown(op) => op,
+        }
+    }
+
+    // the opcode with its operands as letters, e.g. "8XY4", as opcode tables list them
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Instruction::Nop => "0000",
+            Instruction::Cls => "00E0",
+            Instruction::Ret => "00EE",
+            Instruction::Low => "00FE",
+            Instruction::High => "00FF",
+            Instruction::Jp(_) => "1NNN",
+            Instruction::Call(_) => "2NNN",
+            Instruction::SeImm { .. } => "3XNN",
+            Instruction::SneImm { .. } => "4XNN",
+            Instruction::Se { .. } => "5XY0",
+            Instruction::LdImm { .. } => "6XNN",
+            Instruction::AddImm { .. } => "7XNN",
+            Instruction::Ld { .. } => "8XY0",
+            Instruction::Or { .. } => "8XY1",
+            Instruction::And { .. } => "8XY2",
+            Instruction::Xor { .. } => "8XY3",
+            Instruction::Add { .. } => "8XY4",
+            Instruction::Sub { .. } => "8XY5",
+            Instruction::Shr { .. } => "8XY6",
+            Instruction::Subn { .. } => "8XY7",
+            Instruction::Shl { .. } => "8XYE",
+            Instruction::Sne { .. } => "9XY0",
+            Instruction::LdI(_) => "ANNN",
+            Instruction::JpV0(_) => "BNNN",
+            Instruction::Rnd { .. } => "CXNN",
+            Instruction::Drw { .. } => "DXYN",
+            Instruction::Skp(_) => "EX9E",
+            Instruction::Sknp(_) => "EXA1",
+            Instruction::LdVxDt(_) => "FX07",
+            Instruction::LdVxK(_) => "FX0A",
+            Instruction::LdDtVx(_) => "FX15",
+            Instruction::LdStVx(_) => "FX18",
+            Instruction::AddI(_) => "FX1E",
+            Instruction::LdF(_) => "FX29",
+            Instruction::LdB(_) => "FX33",
+            Instruction::Store(_) => "FX55",
+            Instruction::Load(_) => "FX65",
+            Instruction::Unknown(_) => "????",
+        }
+    }
+
+    // skips jump over the next instruction when their condition holds
+    pub fn is_skip(&self) -> bool {
+        matches!(
+            self,
+            Instruction::SeImm { .. }
+                | Instruction::SneImm { .. }
+                | Instruction::Se { .. }
+                | Instruction::Sne { .. }
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_)
+        )
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Instruction::Nop => write!(f, "NOP"),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Low => write!(f, "LOW"),
+            Instruction::High => write!(f, "HIGH"),
+            Instruction::Jp(nnn) => write!(f, "JP {:#05X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL {:#05X}", nnn),
+            Instruction::SeImm { x, nn } => write!(f, "SE V{:X}, {:#04X}", x, nn),
+            Instruction::SneImm { x, nn } => write!(f, "SNE V{:X}, {:#04X}", x, nn),
+            Instruction::Se { x, y } => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LdImm { x, nn } => write!(f, "LD V{:X}, {:#04X}", x, nn),
+            Instruction::AddImm { x, nn } => write!(f, "ADD V{:X}, {:#04X}", x, nn),
+            Instruction::Ld { x, y } => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or { x, y } => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And { x, y } => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor { x, y } => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add { x, y } => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub { x, y } => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr { x, y } => write!(f, "SHR V{:X}, V{:X}", x, y),
+            Instruction::Subn { x, y } => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl { x, y } => write!(f, "SHL V{:X}, V{:X}", x, y),
+            Instruction::Sne { x, y } => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(nnn) => write!(f, "LD I, {:#05X}", nnn),
+            Instruction::JpV0(nnn) => write!(f, "JP V0, {:#05X}", nnn),
+            Instruction::Rnd { x, nn } => write!(f, "RND V{:X}, {:#04X}", x, nn),
+            Instruction::Drw { x, y, n } => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp(x) => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LdVxDt(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdVxK(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdF(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LdB(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::Store(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::Load(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::Unknown(op) => write!(f, "DW {:#06X}", op),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    pub addr: u16,
+    pub op: u16,
+    pub instruction: Instruction,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:03X}: {:04X}  {}",
+            self.addr, self.op, self.instruction
+        )
+    }
+}
+
+impl Line {
+    // the instruction the way asm reads it back. Opcodes decode() loses bits of, like 5XY1, come
+    // out as DW so they still assemble to the same bytes
+    pub fn source(&self) -> String {
+        if self.instruction.encode() == self.op {
+            self.instruction.to_string()
+        } else {
+            format!("DW {:#06X}", self.op)
+        }
+    }
+}
+
+// decodes every two bytes from start, so data mixed in with code shows up as instructions too.
+// A trailing odd byte is ignored
+pub fn disassemble(ram: &[u8], start: u16, count: usize) -> Vec<Line> {
+    let mut lines = Vec::with_capacity(count);
+    let mut addr = start as usize;
+    while lines.len() < count && addr + 1 < ram.len() {
+        let op = ((ram[addr] as u16) << 8) | ram[addr + 1] as u16;
+        lines.push(Line {
+            addr: addr as u16,
+            op,
+            instruction: Instruction::decode(op),
+        });
+        addr += 2;
+    }
+    lines
+}
@@ -0,0 +1,95 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timer {
+    Delay,
+    Sound,
+}
+
+// things that happen inside Emu that a frontend or tool may want to react to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    ScreenCleared,
+    SpriteDrawn {
+        x: u8,
+        y: u8,
+        height: u8,
+        collision: bool,
+    },
+    // also clears the screen
+    ResolutionChanged {
+        hires: bool,
+    },
+    SoundStarted,
+    SoundStopped,
+    // only sent when the wait starts, not for every tick spent waiting
+    WaitingForKey {
+        register: u8,
+    },
+    SubroutineCall {
+        from: u16,
+        to: u16,
+    },
+    SubroutineReturn {
+        to: u16,
+    },
+    TimerExpired(Timer),
+    Fault(Fault),
+}
+
+// anything that can be subscribed to an Emu, closures included
+pub trait Observer: Send {
+    fn on_event(&mut self, event: &Event);
+}
+
+impl<F> Observer for F
+where
+    F: FnMut(&Event) + Send,
+{
+    fn on_event(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub(crate) usize);
+
+// a fault stops the emulator until it is reset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    // address of the instruction that faulted
+    pub pc: u16,
+    pub kind: FaultKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    StackOverflow,
+    StackUnderflow,
+    InvalidOpcode(u16),
+    MemoryOutOfBounds(usize),
+    InvalidKey(u8),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            FaultKind::StackOverflow => write!(f, "stack overflow at {:#05X}", self.pc),
+            FaultKind::StackUnderflow => write!(f, "stack underflow at {:#05X}", self.pc),
+            FaultKind::InvalidOpcode(op) => {
+                write!(f, "invalid opcode {:04X} at {:#05X}", op, self.pc)
+            }
+            FaultKind::MemoryOutOfBounds(addr) => write!(
+                f,
+                "memory access out of bounds ({:#X}) at {:#05X}",
+                addr, self.pc
+            ),
+            FaultKind::InvalidKey(key) => {
+                write!(f, "invalid key {:#X} at {:#05X}", key, self.pc)
+            }
+        }
+    }
+}
+
+impl Error for Fault {}
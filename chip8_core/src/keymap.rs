@@ -0,0 +1,200 @@
+// maps physical keys to the chip-8 keypad. Keys are named by their position using the same codes
+// as the browser's KeyboardEvent.code ("KeyQ", "Digit1", "Numpad7", ...) so a layout works the
+// same whatever the keyboard's language or modifiers. Frontends translate their own key codes
+// into these names
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::NUM_KEYS;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Keymap {
+    // code -> chip-8 key
+    bindings: BTreeMap<String, u8>,
+}
+
+#[derive(Debug)]
+pub enum KeymapError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    Json(serde_json::Error),
+    InvalidKey { code: String, key: u8 },
+    UnknownFormat,
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::Io(e) => write!(f, "{}", e),
+            KeymapError::Toml(e) => write!(f, "{}", e),
+            KeymapError::Json(e) => write!(f, "{}", e),
+            KeymapError::InvalidKey { code, key } => {
+                write!(
+                    f,
+                    "{} is bound to {:#X} but the keypad only goes up to 0xF",
+                    code, key
+                )
+            }
+            KeymapError::UnknownFormat => write!(f, "keymaps must be .toml or .json files"),
+        }
+    }
+}
+
+impl Error for KeymapError {}
+
+// the 4x4 block under the number row, laid out like the COSMAC VIP keypad
+const BLOCK_KEYS: [u8; NUM_KEYS] = [
+    0x1, 0x2, 0x3, 0xC, //
+    0x4, 0x5, 0x6, 0xD, //
+    0x7, 0x8, 0x9, 0xE, //
+    0xA, 0x0, 0xB, 0xF, //
+];
+
+impl Keymap {
+    pub const PRESETS: [&'static str; 3] = ["qwerty", "azerty", "numpad"];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 1234 / QWER / ASDF / ZXCV
+    pub fn qwerty() -> Self {
+        Self::block([
+            "Digit1", "Digit2", "Digit3", "Digit4", "KeyQ", "KeyW", "KeyE", "KeyR", "KeyA", "KeyS",
+            "KeyD", "KeyF", "KeyZ", "KeyX", "KeyC", "KeyV",
+        ])
+    }
+
+    // 1234 / AZER / QSDF / WXCV. Codes are positional so these are the same physical keys as the
+    // qwerty preset, it's here so AZERTY users can pick the layout by the labels they see
+    pub fn azerty() -> Self {
+        Self::qwerty()
+    }
+
+    // the hex keypad on a numeric keypad: digits are themselves and / * - + Enter . are A to F
+    pub fn numpad() -> Self {
+        let mut keymap = Self::new();
+        for digit in 0..10u8 {
+            keymap.bindings.insert(format!("Numpad{}", digit), digit);
+        }
+        for (code, key) in [
+            ("NumpadDivide", 0xA),
+            ("NumpadMultiply", 0xB),
+            ("NumpadSubtract", 0xC),
+            ("NumpadAdd", 0xD),
+            ("NumpadEnter", 0xE),
+            ("NumpadDecimal", 0xF),
+        ] {
+            keymap.bindings.insert(code.to_string(), key);
+        }
+        keymap
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "qwerty" => Some(Self::qwerty()),
+            "azerty" => Some(Self::azerty()),
+            "numpad" | "vip" => Some(Self::numpad()),
+            _ => None,
+        }
+    }
+
+    fn block(codes: [&str; NUM_KEYS]) -> Self {
+        let bindings = codes
+            .iter()
+            .zip(BLOCK_KEYS)
+            .map(|(code, key)| (code.to_string(), key))
+            .collect();
+        Self { bindings }
+    }
+
+    pub fn bind(&mut self, code: &str, key: u8) -> Result<(), KeymapError> {
+        if key as usize >= NUM_KEYS {
+            return Err(KeymapError::InvalidKey {
+                code: code.to_string(),
+                key,
+            });
+        }
+        self.bindings.insert(code.to_string(), key);
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, code: &str) -> Option<u8> {
+        self.bindings.remove(code)
+    }
+
+    // the chip-8 key for a code, ready to pass to Emu::keypress
+    pub fn key(&self, code: &str) -> Option<usize> {
+        self.bindings.get(code).map(|&key| key as usize)
+    }
+
+    pub fn codes_for(&self, key: u8) -> impl Iterator<Item = &str> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, &k)| k == key)
+            .map(|(code, _)| code.as_str())
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, u8)> + '_ {
+        self.bindings
+            .iter()
+            .map(|(code, &key)| (code.as_str(), key))
+    }
+
+    // keymap files are a flat table of code = key, e.g. `KeyQ = 4`
+    pub fn from_toml(s: &str) -> Result<Self, KeymapError> {
+        toml::from_str::<Self>(s)
+            .map_err(KeymapError::Toml)?
+            .validated()
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string(self).expect("a map of strings to numbers always serializes")
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, KeymapError> {
+        serde_json::from_str::<Self>(s)
+            .map_err(KeymapError::Json)?
+            .validated()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a map of strings to numbers always serializes")
+    }
+
+    // the format is picked from the extension
+    pub fn load(path: &Path) -> Result<Self, KeymapError> {
+        let contents = fs::read_to_string(path).map_err(KeymapError::Io)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::from_toml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(KeymapError::UnknownFormat),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), KeymapError> {
+        let contents = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => self.to_toml(),
+            Some("json") => self.to_json(),
+            _ => return Err(KeymapError::UnknownFormat),
+        };
+        fs::write(path, contents).map_err(KeymapError::Io)
+    }
+
+    fn validated(self) -> Result<Self, KeymapError> {
+        if let Some((code, &key)) = self.bindings.iter().find(|(_, &k)| k as usize >= NUM_KEYS) {
+            return Err(KeymapError::InvalidKey {
+                code: code.clone(),
+                key,
+            });
+        }
+        Ok(self)
+    }
+}
@@ -1,13 +1,40 @@
 use rand::random;
+use std::error::Error;
+use std::fmt;
+
+pub mod asm;
+pub mod audio;
+#[cfg(feature = "capture")]
+pub mod capture;
+pub mod cfg;
+mod config;
+pub mod debug;
+pub mod disasm;
+mod events;
+pub mod keymap;
+pub mod lint;
+pub mod octo;
+mod quirks;
+pub mod render;
+pub mod romdb;
+pub mod runner;
+mod state;
+
+pub use config::{
+    ConfigError, MachineConfig, DEFAULT_RAM_SIZE, DEFAULT_STACK_DEPTH, DEFAULT_START_ADDR,
+};
+pub use events::{Event, Fault, FaultKind, Observer, ObserverId, Timer};
+pub use quirks::Quirks;
+pub use state::{SaveState, StateError};
 
 pub const SCREEN_WIDTH: usize = 64;
 pub const SCREEN_HEIGHT: usize = 32;
+// the SUPER-CHIP hi-res mode, switched on with 00FF and off with 00FE
+pub const HIRES_WIDTH: usize = 128;
+pub const HIRES_HEIGHT: usize = 64;
 
-const RAM_SIZE: usize = 4096;
 const NUM_REGS: usize = 16;
-const STACK_SIZE: usize = 16;
 const NUM_KEYS: usize = 16;
-const START_ADDR: u16 = 0x200;
 const FONTSET_SIZE: usize = 80;
 const FONTSET: [u8; FONTSET_SIZE] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -30,31 +57,60 @@ const FONTSET: [u8; FONTSET_SIZE] = [
 
 // delay timer and sound timer
 pub struct Emu {
+    config: MachineConfig,
+    quirks: Quirks,
     pc: u16,
-    ram: [u8; RAM_SIZE],
-    screen: [bool; SCREEN_WIDTH * SCREEN_HEIGHT],
+    ram: Vec<u8>,
+    hires: bool,
+    // width * height pixels for the current resolution
+    screen: Vec<bool>,
     v_reg: [u8; NUM_REGS],
     i_reg: u16,
     sp: u16,
-    stack: [u16; STACK_SIZE],
+    stack: Vec<u16>,
     keys: [bool; NUM_KEYS],
     dt: u8,
     st: u8,
+    waiting_for_key: bool,
+    fault: Option<Fault>,
+    observers: Vec<(ObserverId, Box<dyn Observer>)>,
+    next_observer_id: usize,
+    queue_events: bool,
+    events: Vec<Event>,
 }
 
 impl Emu {
     pub fn new() -> Self {
+        Self::from_config(MachineConfig::default())
+    }
+
+    pub fn with_config(config: MachineConfig) -> Result<Self, ConfigError> {
+        config.validate()?;
+        Ok(Self::from_config(config))
+    }
+
+    // config must already be validated
+    fn from_config(config: MachineConfig) -> Self {
         let mut new_emu = Self {
-            pc: START_ADDR,
-            ram: [0; RAM_SIZE],
-            screen: [false; SCREEN_WIDTH * SCREEN_HEIGHT],
+            config,
+            quirks: Quirks::default(),
+            pc: config.start_addr,
+            ram: vec![0; config.ram_size],
+            hires: false,
+            screen: vec![false; SCREEN_WIDTH * SCREEN_HEIGHT],
             v_reg: [0; NUM_REGS],
             i_reg: 0,
             sp: 0,
-            stack: [0; STACK_SIZE],
+            stack: vec![0; config.stack_depth],
             keys: [false; NUM_KEYS],
             dt: 0,
             st: 0,
+            waiting_for_key: false,
+            fault: None,
+            observers: Vec::new(),
+            next_observer_id: 0,
+            queue_events: false,
+            events: Vec::new(),
         };
 
         new_emu.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
@@ -63,36 +119,131 @@ impl Emu {
     }
 
     pub fn reset(&mut self) {
-        self.pc = START_ADDR;
-        self.ram = [0; RAM_SIZE];
-        self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+        self.pc = self.config.start_addr;
+        self.ram.fill(0);
+        self.hires = false;
+        self.screen = vec![false; SCREEN_WIDTH * SCREEN_HEIGHT];
         self.v_reg = [0; NUM_REGS];
         self.i_reg = 0;
         self.sp = 0;
-        self.stack = [0; STACK_SIZE];
+        self.stack.fill(0);
         self.keys = [false; NUM_KEYS];
         self.dt = 0;
         self.st = 0;
+        self.waiting_for_key = false;
+        self.fault = None;
+        self.events.clear();
         self.ram[..FONTSET_SIZE].copy_from_slice(&FONTSET);
     }
 
+    pub fn config(&self) -> &MachineConfig {
+        &self.config
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    // takes effect from the next instruction and survives reset
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
     pub fn tick(&mut self) {
-        let op = self.fetch();
-        self.execute(op);
+        if self.fault.is_some() {
+            return;
+        }
+
+        let pc = self.pc;
+        let result = match self.fetch() {
+            Ok(op) => self.execute(op),
+            Err(kind) => Err(kind),
+        };
+
+        if let Err(kind) = result {
+            let fault = Fault { pc, kind };
+            self.fault = Some(fault);
+            self.emit(Event::Fault(fault));
+        }
+    }
+
+    // one 60Hz frame worth of instructions followed by a timer tick
+    pub fn run_frame(&mut self, ticks_per_frame: usize) {
+        for _ in 0..ticks_per_frame {
+            self.tick();
+        }
+        self.tick_timers();
+    }
+
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.waiting_for_key
+    }
+
+    pub fn subscribe(&mut self, observer: impl Observer + 'static) -> ObserverId {
+        let id = ObserverId(self.next_observer_id);
+        self.next_observer_id += 1;
+        self.observers.push((id, Box::new(observer)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: ObserverId) {
+        self.observers.retain(|(observer_id, _)| *observer_id != id);
+    }
+
+    // when enabled every event is also kept until drain_events is called, for frontends that
+    // would rather poll once a frame than register a callback
+    pub fn set_event_queue(&mut self, enabled: bool) {
+        self.queue_events = enabled;
+        if !enabled {
+            self.events.clear();
+        }
+    }
+
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    fn emit(&mut self, event: Event) {
+        for (_, observer) in self.observers.iter_mut() {
+            observer.on_event(&event);
+        }
+        if self.queue_events {
+            self.events.push(event);
+        }
     }
 
-    fn fetch(&mut self) -> u16 {
-        let higher_byte = self.ram[self.pc as usize] as u16;
-        let lower_byte = self.ram[(self.pc + 1) as usize] as u16;
+    fn fetch(&mut self) -> Result<u16, FaultKind> {
+        let pc = self.pc as usize;
+        self.check_range(pc, 2)?;
+        let higher_byte = self.ram[pc] as u16;
+        let lower_byte = self.ram[pc + 1] as u16;
         // each operation is two bytes so we move the higher byte to the left and then bitwise or
         // the lower byte
         let op: u16 = (higher_byte << 8) | lower_byte;
-        self.pc += 2;
+        self.pc = self.pc.wrapping_add(2);
+
+        Ok(op)
+    }
 
-        op
+    fn check_range(&self, addr: usize, len: usize) -> Result<(), FaultKind> {
+        if addr + len > self.ram.len() {
+            return Err(FaultKind::MemoryOutOfBounds(addr + len - 1));
+        }
+        Ok(())
     }
 
-    fn execute(&mut self, op: u16) {
+    fn key(&self, key: u8) -> Result<bool, FaultKind> {
+        self.keys
+            .get(key as usize)
+            .copied()
+            .ok_or(FaultKind::InvalidKey(key))
+    }
+
+    fn execute(&mut self, op: u16) -> Result<(), FaultKind> {
         // splits the two bytes in to nibbles or hex digits
         // e.g. for the first digit 1101 1010 1100 1110 & 1111 0000 0000 0000 = 1101
         // you then move it to the front of the two bytes
@@ -103,31 +254,39 @@ impl Emu {
 
         match (nibble1, nibble2, nibble3, nibble4) {
             // NOP
-            (0, 0, 0, 0) => return,
+            (0, 0, 0, 0) => (),
             // clear screen
             (0, 0, 0xE, 0) => {
-                self.screen = [false; SCREEN_WIDTH * SCREEN_HEIGHT];
+                self.screen.fill(false);
+                self.emit(Event::ScreenCleared);
             }
             // return from subroutine
             (0, 0, 0xE, 0xE) => {
-                let addr = self.pop();
+                let addr = self.pop()?;
                 self.pc = addr;
+                self.emit(Event::SubroutineReturn { to: addr });
             }
+            // leave hi-res
+            (0, 0, 0xF, 0xE) => self.set_hires(false),
+            // enter hi-res
+            (0, 0, 0xF, 0xF) => self.set_hires(true),
             // jump to address NNN
             (1, _, _, _) => {
                 self.pc = 0xFFF & op;
             }
             // call subroutine
             (2, _, _, _) => {
-                self.push(self.pc);
+                let from = self.pc.wrapping_sub(2);
+                self.push(self.pc)?;
                 self.pc = 0xFFF & op;
+                self.emit(Event::SubroutineCall { from, to: self.pc });
             }
             // skip next if VX == NN
             (3, _, _, _) => {
                 let x = nibble2 as usize;
                 let nn = (op & 0xFF) as u8;
                 if self.v_reg[x] == nn {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // skip next if VX != NN
@@ -135,7 +294,7 @@ impl Emu {
                 let x = nibble2 as usize;
                 let nn = (op & 0xFF) as u8;
                 if self.v_reg[x] != nn {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // skip next if VX == VY
@@ -143,7 +302,7 @@ impl Emu {
                 let x = nibble2 as usize;
                 let y = nibble3 as usize;
                 if self.v_reg[x] == self.v_reg[y] {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // VX = NN
@@ -169,18 +328,27 @@ impl Emu {
                 let x = nibble2 as usize;
                 let y = nibble3 as usize;
                 self.v_reg[x] |= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // VX &= VY
             (8, _, _, 2) => {
                 let x = nibble2 as usize;
                 let y = nibble3 as usize;
                 self.v_reg[x] &= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // VX ^= VY
             (8, _, _, 3) => {
                 let x = nibble2 as usize;
                 let y = nibble3 as usize;
                 self.v_reg[x] ^= self.v_reg[y];
+                if self.quirks.vf_reset {
+                    self.v_reg[0xF] = 0;
+                }
             }
             // VX += VY (carrys)
             (8, _, _, 4) => {
@@ -207,8 +375,13 @@ impl Emu {
             // VX >>= 1
             (8, _, _, 6) => {
                 let x = nibble2 as usize;
-                let dropped = self.v_reg[x] & 1;
-                self.v_reg[x] >>= 1;
+                let src = if self.quirks.shift_vy {
+                    self.v_reg[nibble3 as usize]
+                } else {
+                    self.v_reg[x]
+                };
+                let dropped = src & 1;
+                self.v_reg[x] = src >> 1;
                 self.v_reg[0xF] = dropped;
             }
             // VX = VY - VX
@@ -225,8 +398,13 @@ impl Emu {
             // VX <<= 1
             (8, _, _, 0xE) => {
                 let x = nibble2 as usize;
-                let missed = (self.v_reg[x] >> 7) & 1;
-                self.v_reg[x] <<= 1;
+                let src = if self.quirks.shift_vy {
+                    self.v_reg[nibble3 as usize]
+                } else {
+                    self.v_reg[x]
+                };
+                let missed = (src >> 7) & 1;
+                self.v_reg[x] = src << 1;
                 self.v_reg[0xF] = missed;
             }
             // skip if VX != VY
@@ -234,7 +412,7 @@ impl Emu {
                 let x = self.v_reg[nibble2 as usize];
                 let y = self.v_reg[nibble3 as usize];
                 if x != y {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // I = NNN
@@ -245,7 +423,12 @@ impl Emu {
             // jump to V0 + nnn
             (0xB, _, _, _) => {
                 let nnn = op & 0xFFF;
-                self.pc = (self.v_reg[0] as u16) + nnn;
+                let x = if self.quirks.jump_vx {
+                    nibble2 as usize
+                } else {
+                    0
+                };
+                self.pc = (self.v_reg[x] as u16) + nnn;
             }
             // VX = rand() & nn
             (0xC, _, _, _) => {
@@ -254,24 +437,44 @@ impl Emu {
                 let rng: u8 = random();
                 self.v_reg[x] = rng & nn;
             }
-            // draw DXYN where n = number of rows
+            // draw DXYN where n = number of rows. In hi-res DXY0 draws a 16x16 sprite, two bytes
+            // a row
             (0xD, _, _, _) => {
-                let x_coord = self.v_reg[nibble2 as usize] as u16;
-                let y_coord = self.v_reg[nibble3 as usize] as u16;
-                let num_rows = nibble4;
+                let x_coord = self.v_reg[nibble2 as usize] as usize;
+                let y_coord = self.v_reg[nibble3 as usize] as usize;
+                let (num_rows, row_bytes) = if nibble4 == 0 && self.hires {
+                    (16, 2)
+                } else {
+                    (nibble4 as usize, 1)
+                };
+                let (width, height) = self.display_size();
 
+                self.check_range(self.i_reg as usize, num_rows * row_bytes)?;
                 let mut flipped = false;
                 for line_number in 0..num_rows {
-                    let addr = self.i_reg + line_number as u16;
-                    let pixels = self.ram[addr as usize];
+                    let addr = self.i_reg as usize + line_number * row_bytes;
 
-                    for col_number in 0..8 {
+                    for col_number in 0..row_bytes * 8 {
+                        let pixels = self.ram[addr + col_number / 8];
                         // use a mask to check if the current pixel != 0
-                        if (pixels & (0b1000_0000 >> col_number)) != 0 {
-                            let x = (x_coord + col_number) as usize % SCREEN_WIDTH;
-                            let y = (y_coord + line_number) as usize % SCREEN_HEIGHT;
-
-                            let idx = x + SCREEN_WIDTH * y;
+                        if (pixels & (0b1000_0000 >> (col_number % 8))) != 0 {
+                            // with clipping the sprite's position still wraps but the pixels
+                            // that run off the edge are dropped
+                            let (x, y) = if self.quirks.clipping {
+                                let x = x_coord % width + col_number;
+                                let y = y_coord % height + line_number;
+                                if x >= width || y >= height {
+                                    continue;
+                                }
+                                (x, y)
+                            } else {
+                                (
+                                    (x_coord + col_number) % width,
+                                    (y_coord + line_number) % height,
+                                )
+                            };
+
+                            let idx = x + width * y;
 
                             flipped |= self.screen[idx];
                             self.screen[idx] ^= true;
@@ -284,21 +487,27 @@ impl Emu {
                 } else {
                     self.v_reg[0xF] = 0;
                 }
+                self.emit(Event::SpriteDrawn {
+                    x: x_coord as u8,
+                    y: y_coord as u8,
+                    height: num_rows as u8,
+                    collision: flipped,
+                });
             }
             // skip if key pressed
             (0xE, _, 9, 0xE) => {
                 let vx = self.v_reg[nibble2 as usize];
-                let key = self.keys[vx as usize];
+                let key = self.key(vx)?;
                 if key {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // skip if key not pressed
             (0xE, _, 0xA, 1) => {
                 let vx = self.v_reg[nibble2 as usize];
-                let key = self.keys[vx as usize];
+                let key = self.key(vx)?;
                 if !key {
-                    self.pc += 2;
+                    self.pc = self.pc.wrapping_add(2);
                 }
             }
             // VX = DT
@@ -319,8 +528,12 @@ impl Emu {
                 }
 
                 if !pressed {
-                    self.pc -= 2;
+                    self.pc = self.pc.wrapping_sub(2);
+                    if !self.waiting_for_key {
+                        self.emit(Event::WaitingForKey { register: x as u8 });
+                    }
                 }
+                self.waiting_for_key = !pressed;
             }
             // DT = VX
             (0xF, _, 1, 5) => {
@@ -330,7 +543,13 @@ impl Emu {
             // ST = VX
             (0xF, _, 1, 8) => {
                 let x = nibble2 as usize;
+                let was_sounding = self.st > 0;
                 self.st = self.v_reg[x];
+                match (was_sounding, self.st > 0) {
+                    (false, true) => self.emit(Event::SoundStarted),
+                    (true, false) => self.emit(Event::SoundStopped),
+                    _ => (),
+                }
             }
             // I += VX
             (0xF, _, 1, 0xE) => {
@@ -354,6 +573,7 @@ impl Emu {
                 // Fetch the ones digit by tossing the hundreds and the tens
                 let ones = (vx % 10.0) as u8;
 
+                self.check_range(self.i_reg as usize, 3)?;
                 self.ram[self.i_reg as usize] = hundreds;
                 self.ram[(self.i_reg + 1) as usize] = tens;
                 self.ram[(self.i_reg + 2) as usize] = ones;
@@ -362,55 +582,233 @@ impl Emu {
             (0xF, _, 5, 5) => {
                 let x = nibble2 as usize;
                 let i = self.i_reg as usize;
+                self.check_range(i, x + 1)?;
                 for idx in 0..=x {
                     self.ram[i + idx] = self.v_reg[idx];
                 }
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
+                }
             }
             // load i into V0..VX
             (0xF, _, 6, 5) => {
                 let x = nibble2 as usize;
                 let i = self.i_reg as usize;
+                self.check_range(i, x + 1)?;
                 for idx in 0..=x {
                     self.v_reg[idx] = self.ram[i + idx];
                 }
+                if self.quirks.memory_increment {
+                    self.i_reg = self.i_reg.wrapping_add(x as u16 + 1);
+                }
             }
-            (_, _, _, _) => unimplemented!("Unimplemented op code: {}", op),
+            (_, _, _, _) => return Err(FaultKind::InvalidOpcode(op)),
         }
+
+        Ok(())
     }
 
+    // row by row, display_size() wide
     pub fn get_display(&self) -> &[bool] {
         &self.screen
     }
 
+    // width and height of the display, which changes when a rom switches to or from hi-res
+    pub fn display_size(&self) -> (usize, usize) {
+        if self.hires {
+            (HIRES_WIDTH, HIRES_HEIGHT)
+        } else {
+            (SCREEN_WIDTH, SCREEN_HEIGHT)
+        }
+    }
+
+    // the display on the hi-res grid, see hires_pixels
+    pub fn hires_display(&self) -> Vec<bool> {
+        hires_pixels(&self.screen)
+    }
+
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    // switching clears the screen, like Octo does
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        let (width, height) = self.display_size();
+        self.screen = vec![false; width * height];
+        self.emit(Event::ResolutionChanged { hires });
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    pub fn set_pc(&mut self, pc: u16) {
+        self.pc = pc;
+    }
+
+    pub fn i_reg(&self) -> u16 {
+        self.i_reg
+    }
+
+    pub fn set_i_reg(&mut self, i: u16) {
+        self.i_reg = i;
+    }
+
+    pub fn v_regs(&self) -> &[u8; NUM_REGS] {
+        &self.v_reg
+    }
+
+    // registers past VF are ignored
+    pub fn set_v_reg(&mut self, x: usize, val: u8) {
+        if let Some(reg) = self.v_reg.get_mut(x) {
+            *reg = val;
+        }
+    }
+
+    pub fn sp(&self) -> u16 {
+        self.sp
+    }
+
+    // only the addresses currently pushed, oldest first
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    pub fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn write_ram(&mut self, addr: usize, data: &[u8]) -> Result<(), LoadError> {
+        let end = addr + data.len();
+        if end > self.ram.len() {
+            return Err(LoadError::OutOfBounds {
+                addr,
+                len: data.len(),
+            });
+        }
+        self.ram[addr..end].copy_from_slice(data);
+
+        Ok(())
+    }
+
+    pub fn is_sounding(&self) -> bool {
+        self.st > 0
+    }
+
+    // keys past 0xF are ignored
     pub fn keypress(&mut self, idx: usize, pressed: bool) {
-        self.keys[idx] = pressed;
+        if let Some(key) = self.keys.get_mut(idx) {
+            *key = pressed;
+        }
+    }
+
+    // which keys are held down
+    pub fn keys(&self) -> &[bool; NUM_KEYS] {
+        &self.keys
     }
 
-    pub fn load(&mut self, data: &[u8]) {
-        let start = START_ADDR as usize;
-        let end = (START_ADDR as usize) + data.len();
+    pub fn load(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let start = self.config.start_addr as usize;
+        let end = start + data.len();
+        if end > self.ram.len() {
+            return Err(LoadError::TooLarge {
+                size: data.len(),
+                capacity: self.ram.len() - start,
+            });
+        }
         self.ram[start..end].copy_from_slice(data);
+
+        Ok(())
     }
 
     pub fn tick_timers(&mut self) {
         if self.dt > 0 {
             self.dt -= 1;
+            if self.dt == 0 {
+                self.emit(Event::TimerExpired(Timer::Delay));
+            }
         }
         if self.st > 0 {
-            if self.st == 1 {
-                // make sound here
-            }
             self.st -= 1;
+            if self.st == 0 {
+                self.emit(Event::TimerExpired(Timer::Sound));
+                self.emit(Event::SoundStopped);
+            }
         }
     }
 
-    fn push(&mut self, val: u16) {
+    fn push(&mut self, val: u16) -> Result<(), FaultKind> {
+        if self.sp as usize >= self.stack.len() {
+            return Err(FaultKind::StackOverflow);
+        }
         self.stack[self.sp as usize] = val;
         self.sp += 1;
+        Ok(())
     }
 
-    fn pop(&mut self) -> u16 {
+    fn pop(&mut self) -> Result<u16, FaultKind> {
+        if self.sp == 0 {
+            return Err(FaultKind::StackUnderflow);
+        }
         self.sp -= 1;
-        self.stack[self.sp as usize]
+        Ok(self.stack[self.sp as usize])
+    }
+}
+
+impl Default for Emu {
+    fn default() -> Self {
+        Self::new()
     }
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadError {
+    TooLarge { size: usize, capacity: usize },
+    OutOfBounds { addr: usize, len: usize },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::TooLarge { size, capacity } => write!(
+                f,
+                "rom is {} bytes but only {} bytes fit after the start address",
+                size, capacity
+            ),
+            LoadError::OutOfBounds { addr, len } => {
+                write!(f, "{} bytes at {:#X} don't fit in memory", len, addr)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+// a display of either resolution on the hi-res grid with lo-res pixels doubled, for recordings
+// that have to stay one size when a rom switches resolution
+pub fn hires_pixels(display: &[bool]) -> Vec<bool> {
+    if display.len() == HIRES_WIDTH * HIRES_HEIGHT {
+        return display.to_vec();
+    }
+    (0..HIRES_WIDTH * HIRES_HEIGHT)
+        .map(|idx| {
+            let (x, y) = (idx % HIRES_WIDTH / 2, idx / HIRES_WIDTH / 2);
+            display[x + SCREEN_WIDTH * y]
+        })
+        .collect()
+}
+
+// identifies a rom whatever its file is called. This is the lowercase hex SHA-1 that chip-8 rom
+// databases key their entries by
+pub fn rom_hash(rom: &[u8]) -> String {
+    sha1_smol::Sha1::from(rom).digest().to_string()
+}
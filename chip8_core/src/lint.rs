@@ -0,0 +1,491 @@
+// finds the instructions in a rom whose behaviour depends on its Quirks, so the right profile can
+// be picked without trial and error. Only code cfg::analyze can reach is looked at, and like it
+// registers and I are only tracked within a basic block, so this errs towards missing things
+// rather than reporting ones that aren't there
+
+use std::fmt;
+
+use crate::cfg::{self, Cfg};
+use crate::disasm::{Instruction, Line};
+use crate::{LoadError, Quirks, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+const VF: u8 = 0xF;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    // 8XY6 or 8XYE with X != Y, which shifts VY or VX depending on shift_vy
+    Shift { x: u8, y: u8 },
+    // FX55 or FX65, then I used again at used without being set in between
+    MemoryThenI { used: u16 },
+    // BNNN with X != 0, which adds V0 or VX depending on jump_vx
+    Jump0 { x: u8 },
+    // DXYN at a known position past the right or bottom edge, n is 0 for a 16x16 DXY0. Only
+    // checked on roms that can't switch to hi-res, since the screen size isn't known otherwise
+    SpriteEdge { x: u16, y: u16, n: u8 },
+    // VX or VY changed by the instruction just before a DXYN, usually something moving that
+    // can reach the edge
+    MovedBeforeDraw { reg: u8 },
+    // 8XY1, 8XY2 or 8XY3 then VF read at used, VF being cleared or kept depending on vf_reset
+    LogicThenVf { used: u16 },
+    // an 8XYN with VF as an operand, where the flag and the result fight over it. No quirk
+    // changes this, it's only worth a look
+    VfOperand,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub addr: u16,
+    pub kind: Kind,
+}
+
+impl Finding {
+    // the Quirks field the finding depends on, if any
+    pub fn quirk(&self) -> Option<&'static str> {
+        match self.kind {
+            Kind::Shift { .. } => Some("shift_vy"),
+            Kind::MemoryThenI { .. } => Some("memory_increment"),
+            Kind::Jump0 { .. } => Some("jump_vx"),
+            Kind::SpriteEdge { .. } | Kind::MovedBeforeDraw { .. } => Some("clipping"),
+            Kind::LogicThenVf { .. } => Some("vf_reset"),
+            Kind::VfOperand => None,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03X}: ", self.addr)?;
+        match self.kind {
+            Kind::Shift { x, y } => write!(f, "shifts V{:X} into V{:X}", y, x),
+            Kind::MemoryThenI { used } => write!(f, "I is used again at {:03X}", used),
+            Kind::Jump0 { x } => write!(f, "jump offset by V0 or V{:X}", x),
+            Kind::SpriteEdge { x, y, n: 0 } => {
+                write!(f, "16x16 sprite at {},{} crosses the edge", x, y)
+            }
+            Kind::SpriteEdge { x, y, n } => {
+                write!(f, "{} row sprite at {},{} crosses the edge", n, x, y)
+            }
+            Kind::MovedBeforeDraw { reg } => {
+                write!(f, "V{:X} changes right before the sprite is drawn", reg)
+            }
+            Kind::LogicThenVf { used } => write!(f, "VF is read at {:03X}", used),
+            Kind::VfOperand => write!(f, "VF is an operand"),
+        }?;
+        match self.quirk() {
+            Some(quirk) => write!(f, " ({})", quirk),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    // the quirks that look most likely to run the rom. What the rom expects is only clear for
+    // some findings, the others just say the quirk matters
+    pub suggested: Quirks,
+    // why suggested was picked, one reason per line
+    pub reasons: Vec<String>,
+}
+
+// fails the same way cfg::analyze does, for roms too big for 64K
+pub fn lint(rom: &[u8]) -> Result<Report, LoadError> {
+    let cfg = cfg::analyze(rom)?;
+    // whether a 00FF can be reached, after which the screen is 128x64
+    let hires = code(&cfg).any(|line| line.instruction == Instruction::High);
+    let mut findings = Vec::new();
+    for block in cfg.blocks.values() {
+        lint_block(block, hires, &mut findings);
+    }
+    findings.sort_by_key(|finding| finding.addr);
+    let (suggested, mut reasons) = suggest(&cfg, &findings);
+    if hires {
+        reasons.push("sprite edges aren't checked, the rom can switch to hi-res".to_string());
+    }
+    Ok(Report {
+        findings,
+        suggested,
+        reasons,
+    })
+}
+
+fn lint_block(block: &cfg::Block, hires: bool, findings: &mut Vec<Finding>) {
+    // register values set by LD VX, NN that haven't been changed since
+    let mut known: [Option<u8>; 16] = [None; 16];
+    // the FX55 or FX65 since I was last set
+    let mut copied = None;
+    // the logic op since VF was last written
+    let mut logic = None;
+    let mut previous: Option<Instruction> = None;
+
+    for line in &block.lines {
+        let addr = line.addr;
+        let instruction = line.instruction;
+
+        if uses_i(instruction) {
+            if let Some(at) = copied.take() {
+                findings.push(Finding {
+                    addr: at,
+                    kind: Kind::MemoryThenI { used: addr },
+                });
+            }
+        }
+        if reads_vf(instruction) {
+            if let Some(at) = logic.take() {
+                findings.push(Finding {
+                    addr: at,
+                    kind: Kind::LogicThenVf { used: addr },
+                });
+            }
+        }
+
+        let mut finding = |kind| findings.push(Finding { addr, kind });
+        match instruction {
+            Instruction::Shr { x, y } | Instruction::Shl { x, y } if x != y => {
+                finding(Kind::Shift { x, y })
+            }
+            Instruction::JpV0(nnn) if nnn >> 8 != 0 => finding(Kind::Jump0 {
+                x: (nnn >> 8) as u8,
+            }),
+            Instruction::Drw { x, y, n } => {
+                let position = known[x as usize].zip(known[y as usize]);
+                if let Some((vx, vy)) = position.filter(|_| !hires) {
+                    // positions wrap before drawing, it's only the sprite that's cut off
+                    let (px, py) = (vx as usize % SCREEN_WIDTH, vy as usize % SCREEN_HEIGHT);
+                    let (width, height) = if n == 0 { (16, 16) } else { (8, n as usize) };
+                    if px + width > SCREEN_WIDTH || py + height > SCREEN_HEIGHT {
+                        finding(Kind::SpriteEdge {
+                            x: px as u16,
+                            y: py as u16,
+                            n,
+                        });
+                    }
+                }
+                if let Some(reg) = previous
+                    .and_then(arithmetic_target)
+                    .filter(|&reg| reg == x || reg == y)
+                {
+                    finding(Kind::MovedBeforeDraw { reg });
+                }
+            }
+            _ => {}
+        }
+        if let Some((x, y)) = alu_operands(instruction) {
+            if x == VF || y == VF {
+                finding(Kind::VfOperand);
+            }
+        }
+
+        match instruction {
+            Instruction::Store(_) | Instruction::Load(_) => copied = Some(addr),
+            // the ones that load I, rather than move it along
+            Instruction::LdI(_) | Instruction::LdF(_) => copied = None,
+            _ => {}
+        }
+        if matches!(
+            instruction,
+            Instruction::Or { .. } | Instruction::And { .. } | Instruction::Xor { .. }
+        ) {
+            logic = Some(addr);
+        } else if writes(instruction).contains(&VF) {
+            logic = None;
+        }
+
+        let value = match instruction {
+            Instruction::LdImm { nn, .. } => Some(nn),
+            Instruction::AddImm { x, nn } => known[x as usize].map(|v| v.wrapping_add(nn)),
+            Instruction::Ld { y, .. } => known[y as usize],
+            _ => None,
+        };
+        for reg in writes(instruction) {
+            known[reg as usize] = None;
+        }
+        if let Instruction::LdImm { x, .. }
+        | Instruction::AddImm { x, .. }
+        | Instruction::Ld { x, .. } = instruction
+        {
+            known[x as usize] = value;
+        }
+        previous = Some(instruction);
+    }
+}
+
+// the Quirks the findings and the opcodes point to
+fn suggest(cfg: &Cfg, findings: &[Finding]) -> (Quirks, Vec<String>) {
+    let mut reasons = Vec::new();
+    let schip = code(cfg).any(|line| {
+        matches!(
+            line.instruction,
+            Instruction::Low | Instruction::High | Instruction::Drw { n: 0, .. }
+        )
+    });
+    let mut suggested = if schip {
+        reasons.push("uses SUPER-CHIP instructions".to_string());
+        Quirks::schip()
+    } else {
+        Quirks::modern()
+    };
+
+    // shifting another register only makes sense if VY is what gets shifted
+    let shifts = count(findings, |k| matches!(k, Kind::Shift { .. }));
+    // using I straight after a copy only makes sense if the copy left it alone
+    let reuses = count(findings, |k| matches!(k, Kind::MemoryThenI { .. }));
+    if shifts > 0 && !schip {
+        reasons.push(format!(
+            "{} shift{} of another register, which the original interpreter did",
+            shifts,
+            plural(shifts)
+        ));
+        suggested = Quirks::vip();
+    }
+    if reuses > 0 && suggested.memory_increment {
+        reasons.push(format!(
+            "{} use{} of I right after FX55 or FX65, as if they left I alone",
+            reuses,
+            plural(reuses)
+        ));
+        suggested.memory_increment = false;
+    }
+    if findings.iter().all(|f| f.quirk().is_none()) && !schip {
+        reasons.push("nothing depends on quirks, any profile will do".to_string());
+    }
+
+    // other quirks that matter but don't say which way, for the record
+    for quirk in ["clipping", "jump_vx", "vf_reset"] {
+        let n = findings.iter().filter(|f| f.quirk() == Some(quirk)).count();
+        if n > 0 {
+            reasons.push(format!(
+                "{} matters to {} finding{}, try both ways if it looks wrong",
+                quirk,
+                n,
+                plural(n)
+            ));
+        }
+    }
+    (suggested, reasons)
+}
+
+fn code(cfg: &Cfg) -> impl Iterator<Item = &Line> {
+    cfg.blocks.values().flat_map(|block| &block.lines)
+}
+
+fn count(findings: &[Finding], f: impl Fn(&Kind) -> bool) -> usize {
+    findings.iter().filter(|finding| f(&finding.kind)).count()
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+fn uses_i(instruction: Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Drw { .. }
+            | Instruction::Store(_)
+            | Instruction::Load(_)
+            | Instruction::LdB(_)
+            | Instruction::AddI(_)
+    )
+}
+
+// the registers of an 8XYN
+fn alu_operands(instruction: Instruction) -> Option<(u8, u8)> {
+    match instruction {
+        Instruction::Ld { x, y }
+        | Instruction::Or { x, y }
+        | Instruction::And { x, y }
+        | Instruction::Xor { x, y }
+        | Instruction::Add { x, y }
+        | Instruction::Sub { x, y }
+        | Instruction::Shr { x, y }
+        | Instruction::Subn { x, y }
+        | Instruction::Shl { x, y } => Some((x, y)),
+        _ => None,
+    }
+}
+
+fn reads_vf(instruction: Instruction) -> bool {
+    match instruction {
+        Instruction::SeImm { x, .. }
+        | Instruction::SneImm { x, .. }
+        | Instruction::AddImm { x, .. }
+        | Instruction::Skp(x)
+        | Instruction::Sknp(x)
+        | Instruction::LdDtVx(x)
+        | Instruction::LdStVx(x)
+        | Instruction::AddI(x)
+        | Instruction::LdF(x)
+        | Instruction::LdB(x) => x == VF,
+        Instruction::Se { x, y } | Instruction::Sne { x, y } => x == VF || y == VF,
+        Instruction::Drw { x, y, .. } => x == VF || y == VF,
+        Instruction::Store(x) => x == VF,
+        _ => alu_operands(instruction).is_some_and(|(x, y)| x == VF || y == VF),
+    }
+}
+
+// the registers an instruction changes
+fn writes(instruction: Instruction) -> Vec<u8> {
+    match instruction {
+        Instruction::LdImm { x, .. }
+        | Instruction::AddImm { x, .. }
+        | Instruction::Ld { x, .. }
+        | Instruction::Rnd { x, .. }
+        | Instruction::LdVxDt(x)
+        | Instruction::LdVxK(x) => vec![x],
+        Instruction::Or { x, .. }
+        | Instruction::And { x, .. }
+        | Instruction::Xor { x, .. }
+        | Instruction::Add { x, .. }
+        | Instruction::Sub { x, .. }
+        | Instruction::Shr { x, .. }
+        | Instruction::Subn { x, .. }
+        | Instruction::Shl { x, .. } => vec![x, VF],
+        Instruction::Drw { .. } => vec![VF],
+        Instruction::Load(x) => (0..=x).collect(),
+        // a call is the only thing taken to change all of them, there's no telling what the
+        // subroutine does
+        Instruction::Call(_) => (0..16).collect(),
+        Instruction::Nop
+        | Instruction::Cls
+        | Instruction::Ret
+        | Instruction::Low
+        | Instruction::High
+        | Instruction::Jp(_)
+        | Instruction::SeImm { .. }
+        | Instruction::SneImm { .. }
+        | Instruction::Se { .. }
+        | Instruction::Sne { .. }
+        | Instruction::LdI(_)
+        | Instruction::JpV0(_)
+        | Instruction::Skp(_)
+        | Instruction::Sknp(_)
+        | Instruction::LdDtVx(_)
+        | Instruction::LdStVx(_)
+        | Instruction::AddI(_)
+        | Instruction::LdF(_)
+        | Instruction::LdB(_)
+        | Instruction::Store(_)
+        | Instruction::Unknown(_) => vec![],
+    }
+}
+
+// the register an arithmetic instruction moves
+fn arithmetic_target(instruction: Instruction) -> Option<u8> {
+    match instruction {
+        Instruction::AddImm { x, .. }
+        | Instruction::Add { x, .. }
+        | Instruction::Sub { x, .. }
+        | Instruction::Subn { x, .. }
+        | Instruction::Shr { x, .. }
+        | Instruction::Shl { x, .. } => Some(x),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(report: &Report) -> Vec<Kind> {
+        report.findings.iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn shifts_of_another_register_suggest_vip() {
+        let report = lint(&[0x82, 0x36]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::Shift { x: 2, y: 3 }]);
+        assert_eq!(report.suggested, Quirks::vip());
+        assert_eq!(
+            report.findings[0].to_string(),
+            "200: shifts V3 into V2 (shift_vy)"
+        );
+    }
+
+    #[test]
+    fn reusing_i_turns_off_memory_increment() {
+        // SHR V2, V3 picks vip, then LD V1, [I] and DRW V0, V1, 5 with the same I
+        let report = lint(&[0x82, 0x36, 0xF1, 0x65, 0xD0, 0x15]).expect("fits");
+        assert_eq!(
+            kinds(&report),
+            [
+                Kind::Shift { x: 2, y: 3 },
+                Kind::MemoryThenI { used: 0x204 }
+            ]
+        );
+        assert_eq!(
+            report.suggested,
+            Quirks {
+                memory_increment: false,
+                ..Quirks::vip()
+            }
+        );
+        assert_eq!(
+            report.reasons,
+            [
+                "1 shift of another register, which the original interpreter did",
+                "1 use of I right after FX55 or FX65, as if they left I alone",
+            ]
+        );
+    }
+
+    #[test]
+    fn font_characters_load_i() {
+        // LD V1, [I] ; LD F, V0 ; DRW V0, V1, 5 draws the digit, not what was copied
+        let report = lint(&[0xF1, 0x65, 0xF0, 0x29, 0xD0, 0x15]).expect("fits");
+        assert!(report.findings.is_empty());
+        assert_eq!(report.suggested, Quirks::modern());
+    }
+
+    #[test]
+    fn sprites_past_the_edge() {
+        // LD V4, 62 ; LD V5, 1 ; DRW V4, V5, 4
+        let report = lint(&[0x64, 62, 0x65, 0x01, 0xD4, 0x54]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::SpriteEdge { x: 62, y: 1, n: 4 }]);
+        // on screen, the previous instruction moved V4 though
+        let report = lint(&[0x64, 0x00, 0x74, 0x01, 0xD4, 0x54]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::MovedBeforeDraw { reg: 4 }]);
+        // LD V4, 50 ; DRW V4, V4, 0 is a 16x16 sprite
+        let report = lint(&[0x64, 50, 0xD4, 0x40]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::SpriteEdge { x: 50, y: 18, n: 0 }]);
+        assert_eq!(
+            report.findings[0].to_string(),
+            "202: 16x16 sprite at 50,18 crosses the edge (clipping)"
+        );
+    }
+
+    #[test]
+    fn sprite_edges_need_lores() {
+        // HIGH ; LD V4, 62 ; DRW V4, V4, 1 is on screen once it's 128x64
+        let report = lint(&[0x00, 0xFF, 0x64, 62, 0xD4, 0x41]).expect("fits");
+        assert!(report.findings.is_empty());
+        assert_eq!(
+            report.reasons.last().unwrap(),
+            "sprite edges aren't checked, the rom can switch to hi-res"
+        );
+    }
+
+    #[test]
+    fn vf_as_an_operand_has_no_quirk() {
+        // ADD VF, V1
+        let report = lint(&[0x8F, 0x14]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::VfOperand]);
+        assert_eq!(report.findings[0].quirk(), None);
+        assert_eq!(report.findings[0].to_string(), "200: VF is an operand");
+        assert_eq!(
+            report.reasons,
+            ["nothing depends on quirks, any profile will do"]
+        );
+    }
+
+    #[test]
+    fn logic_then_vf() {
+        // OR V0, V1 ; SE VF, 0x00
+        let report = lint(&[0x80, 0x11, 0x3F, 0x00]).expect("fits");
+        assert_eq!(kinds(&report), [Kind::LogicThenVf { used: 0x202 }]);
+        assert_eq!(report.findings[0].quirk(), Some("vf_reset"));
+    }
+}
@@ -0,0 +1,1268 @@
+// assembles Octo, the language of the Octo IDE (github.com/JohnEarnest/Octo) that most modern
+// chip-8 programs are written in, e.g.
+//
+//     : main
+//         v0 := 0
+//         loop
+//             i := hex v0
+//             sprite v0 v0 5
+//             v0 += 1
+//             if v0 == 16 then v0 := 0
+//         again
+//
+// Everything is laid out from DEFAULT_START_ADDR. If there's a main label anywhere but the very
+// start, the rom begins with a jump to it. :calc expressions are worked out right to left with no
+// precedence, the same as Octo, so use brackets
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::asm::{AsmError, Assembled};
+use crate::DEFAULT_START_ADDR;
+
+// each instruction set includes the ones before it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Target {
+    #[default]
+    Chip8,
+    Schip,
+    XoChip,
+}
+
+impl Target {
+    pub const NAMES: [&'static str; 3] = ["chip8", "schip", "xochip"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "chip8" | "chip-8" => Some(Target::Chip8),
+            "schip" | "superchip" => Some(Target::Schip),
+            "xochip" | "xo-chip" => Some(Target::XoChip),
+            _ => None,
+        }
+    }
+
+    fn display_name(self) -> &'static str {
+        match self {
+            Target::Chip8 => "CHIP-8",
+            Target::Schip => "SUPER-CHIP",
+            Target::XoChip => "XO-CHIP",
+        }
+    }
+
+    // XO-CHIP has 64K, everything else 4K
+    fn memory_size(self) -> usize {
+        match self {
+            Target::Chip8 | Target::Schip => 0x1000,
+            Target::XoChip => 0x10000,
+        }
+    }
+}
+
+pub fn assemble(source: &str, target: Target) -> Result<Assembled, AsmError> {
+    let tokens = tokenize(source);
+    let end = tokens.back().cloned().unwrap_or(Token {
+        text: String::new(),
+        line: 1,
+        column: 1,
+    });
+    let assembler = Assembler {
+        target,
+        rom: vec![0; target.memory_size()],
+        used: vec![false; target.memory_size()],
+        here: DEFAULT_START_ADDR as usize,
+        labels: HashMap::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        fixups: Vec::new(),
+        flow: Vec::new(),
+        expansions: 0,
+        end,
+        tokens,
+    };
+    assembler.run()
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    text: String,
+    // both count from 1
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    fn is(&self, text: &str) -> bool {
+        self.text == text
+    }
+}
+
+// brackets are tokens of their own, everything else is split on whitespace. # starts a comment
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+    for (i, line) in source.lines().enumerate() {
+        let mut start = None;
+        for (column, c) in line.char_indices().chain([(line.len(), ' ')]) {
+            let bracket = matches!(c, '{' | '}' | '(' | ')');
+            if c.is_whitespace() || bracket || (c == '#' && start.is_none()) {
+                if let Some(s) = start.take() {
+                    tokens.push_back(Token {
+                        text: line[s..column].to_string(),
+                        line: i + 1,
+                        column: s + 1,
+                    });
+                }
+                if c == '#' {
+                    break;
+                }
+                if bracket {
+                    tokens.push_back(Token {
+                        text: c.to_string(),
+                        line: i + 1,
+                        column: column + 1,
+                    });
+                }
+            } else if start.is_none() {
+                start = Some(column);
+            }
+        }
+    }
+    tokens
+}
+
+struct Macro {
+    params: Vec<String>,
+    body: Vec<Token>,
+}
+
+// the places an address goes before its label has been defined
+enum Fixup {
+    // the low 12 bits of the instruction at addr
+    Nnn,
+    // two bytes from addr
+    Long,
+    // the byte after addr gets a nibble and the top of the address, for :unpack
+    UnpackHigh(Option<u8>),
+    // the byte after addr gets the bottom of the address
+    UnpackLow,
+}
+
+// blocks that are still open
+enum Flow {
+    // the jump past the block when the condition fails
+    If {
+        jump: usize,
+        token: Token,
+    },
+    Else {
+        jump: usize,
+        token: Token,
+    },
+    // the jumps out for each while
+    Loop {
+        start: usize,
+        exits: Vec<usize>,
+        token: Token,
+    },
+}
+
+enum Value {
+    Known(i64),
+    Label(Token),
+}
+
+// how many macros can be expanded, in case one expands itself
+const MAX_EXPANSIONS: usize = 100_000;
+
+struct Assembler {
+    tokens: VecDeque<Token>,
+    // where unexpected ends of file are reported
+    end: Token,
+    target: Target,
+    // all of memory, with what's been written to so overlaps are caught
+    rom: Vec<u8>,
+    used: Vec<bool>,
+    here: usize,
+    labels: HashMap<String, u16>,
+    consts: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<(usize, Fixup, Token)>,
+    flow: Vec<Flow>,
+    expansions: usize,
+}
+
+impl Assembler {
+    fn run(mut self) -> Result<Assembled, AsmError> {
+        // a jump to main goes first, unless main is first already
+        let starts_with_main = self.tokens.front().is_some_and(|t| t.is(":"))
+            && self.tokens.get(1).is_some_and(|t| t.is("main"));
+        let has_main = self
+            .tokens
+            .iter()
+            .zip(self.tokens.iter().skip(1))
+            .any(|(a, b)| a.is(":") && b.is("main"));
+        if has_main && !starts_with_main {
+            let main = Token {
+                text: "main".to_string(),
+                line: 1,
+                column: 1,
+            };
+            self.emit_addr(0x1000, Value::Label(main.clone()), &main)?;
+        }
+
+        while let Some(token) = self.tokens.pop_front() {
+            self.statement(token)?;
+        }
+        if let Some(flow) = self.flow.last() {
+            let (token, what) = match flow {
+                Flow::If { token, .. } | Flow::Else { token, .. } => (token, "end"),
+                Flow::Loop { token, .. } => (token, "again"),
+            };
+            return Err(token.error(format!("{} has no matching {}", token.text, what)));
+        }
+
+        for (addr, fixup, token) in std::mem::take(&mut self.fixups) {
+            let value = self
+                .labels
+                .get(&token.text)
+                .copied()
+                .ok_or_else(|| token.error(format!("{} isn't defined", token.text)))?;
+            match fixup {
+                Fixup::Nnn => {
+                    let value = check_addr(value as i64, &token)?;
+                    self.rom[addr] |= (value >> 8) as u8;
+                    self.rom[addr + 1] = value as u8;
+                }
+                Fixup::Long => {
+                    self.rom[addr..addr + 2].copy_from_slice(&value.to_be_bytes());
+                }
+                Fixup::UnpackHigh(nibble) => {
+                    self.rom[addr + 1] = match nibble {
+                        Some(nibble) => nibble << 4 | (value >> 8) as u8 & 0xF,
+                        None => (value >> 8) as u8,
+                    };
+                }
+                Fixup::UnpackLow => self.rom[addr + 1] = value as u8,
+            }
+        }
+
+        let start = DEFAULT_START_ADDR as usize;
+        let end = self
+            .used
+            .iter()
+            .rposition(|&used| used)
+            .map_or(start, |last| last + 1);
+        Ok(Assembled {
+            rom: self.rom[start..end.max(start)].to_vec(),
+            labels: self.labels,
+        })
+    }
+
+    fn next(&mut self) -> Result<Token, AsmError> {
+        self.tokens
+            .pop_front()
+            .ok_or_else(|| self.end.error("the file ends partway through a statement"))
+    }
+
+    fn peek_is(&self, text: &str) -> bool {
+        self.tokens.front().is_some_and(|t| t.is(text))
+    }
+
+    fn expect(&mut self, text: &str) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if !token.is(text) {
+            return Err(token.error(format!("expected {} but found {}", text, token.text)));
+        }
+        Ok(token)
+    }
+
+    fn require(&self, target: Target, token: &Token) -> Result<(), AsmError> {
+        if self.target < target {
+            return Err(token.error(format!(
+                "{} needs {}, this is being assembled for {}",
+                token.text,
+                target.display_name(),
+                self.target.display_name()
+            )));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), AsmError> {
+        if let Some(x) = self.register_of(&token) {
+            return self.register_statement(x, token);
+        }
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                self.define(name, self.here)?;
+            }
+            ":next" => {
+                // the label points at the second byte of the next instruction, for self-modifying
+                // code to overwrite its operand
+                let name = self.name()?;
+                self.define(name, self.here + 1)?;
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.known()?;
+                self.consts.insert(name.text, value as f64);
+            }
+            ":calc" => {
+                let name = self.name()?;
+                let open = self.expect("{")?;
+                let value = self.calc(&open)?;
+                self.consts.insert(name.text, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let x = self.register()?;
+                self.aliases.insert(name.text, x);
+            }
+            ":org" => {
+                let addr = self.known()?;
+                if addr < 0 || addr as usize >= self.target.memory_size() {
+                    return Err(token.error(format!("{:#X} is outside memory", addr)));
+                }
+                self.here = addr as usize;
+            }
+            ":byte" => {
+                let value = self.byte()?;
+                self.emit_byte(value, &token)?;
+            }
+            ":pointer" => {
+                let value = self.value()?;
+                self.emit_long(value, &token)?;
+            }
+            ":call" => {
+                let value = self.value()?;
+                self.emit_addr(0x2000, value, &token)?;
+            }
+            ":unpack" => self.unpack(&token)?,
+            ":macro" => self.define_macro()?,
+            // only meaningful to the Octo IDE
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            "clear" => self.emit(0x00E0, &token)?,
+            "return" | ";" => self.emit(0x00EE, &token)?,
+            "hires" | "lores" | "exit" | "scroll-left" | "scroll-right" => {
+                self.require(Target::Schip, &token)?;
+                let op = match token.text.as_str() {
+                    "hires" => 0x00FF,
+                    "lores" => 0x00FE,
+                    "exit" => 0x00FD,
+                    "scroll-left" => 0x00FC,
+                    _ => 0x00FB,
+                };
+                self.emit(op, &token)?;
+            }
+            "scroll-down" => {
+                self.require(Target::Schip, &token)?;
+                let n = self.nibble()?;
+                self.emit(0x00C0 | n as u16, &token)?;
+            }
+            "scroll-up" => {
+                self.require(Target::XoChip, &token)?;
+                let n = self.nibble()?;
+                self.emit(0x00D0 | n as u16, &token)?;
+            }
+            "plane" => {
+                self.require(Target::XoChip, &token)?;
+                let n = self.nibble()?;
+                if n > 3 {
+                    return Err(token.error("there are only planes 0 to 3"));
+                }
+                self.emit(0xF001 | (n as u16) << 8, &token)?;
+            }
+            "audio" => {
+                self.require(Target::XoChip, &token)?;
+                self.emit(0xF002, &token)?;
+            }
+            "jump" => {
+                let value = self.value()?;
+                self.emit_addr(0x1000, value, &token)?;
+            }
+            "jump0" => {
+                let value = self.value()?;
+                self.emit_addr(0xB000, value, &token)?;
+            }
+            "native" => {
+                let value = self.value()?;
+                self.emit_addr(0x0000, value, &token)?;
+            }
+            "bcd" => {
+                let x = self.register()?;
+                self.emit(0xF033 | x_of(x), &token)?;
+            }
+            "save" | "load" => {
+                let x = self.register()?;
+                let range = self.peek_is("-");
+                if range {
+                    self.require(Target::XoChip, &token)?;
+                    self.next()?;
+                    let y = self.register()?;
+                    let base = if token.is("save") { 0x5002 } else { 0x5003 };
+                    self.emit(base | x_of(x) | y_of(y), &token)?;
+                } else {
+                    let base = if token.is("save") { 0xF055 } else { 0xF065 };
+                    self.emit(base | x_of(x), &token)?;
+                }
+            }
+            "saveflags" | "loadflags" => {
+                self.require(Target::Schip, &token)?;
+                let x = self.register()?;
+                let base = if token.is("saveflags") {
+                    0xF075
+                } else {
+                    0xF085
+                };
+                self.emit(base | x_of(x), &token)?;
+            }
+            "sprite" => {
+                let x = self.register()?;
+                let y = self.register()?;
+                let n = self.nibble()?;
+                self.emit(0xD000 | x_of(x) | y_of(y) | n as u16, &token)?;
+            }
+            "delay" | "buzzer" | "pitch" => {
+                if token.is("pitch") {
+                    self.require(Target::XoChip, &token)?;
+                }
+                self.expect(":=")?;
+                let x = self.register()?;
+                let base = match token.text.as_str() {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.emit(base | x_of(x), &token)?;
+            }
+            "i" => self.i_statement(token)?,
+            "if" => self.if_statement(token)?,
+            "else" => {
+                let jump = match self.flow.pop() {
+                    Some(Flow::If { jump, .. }) => jump,
+                    _ => return Err(token.error("else without an if ... begin")),
+                };
+                let end_jump = self.here;
+                self.emit(0x1000, &token)?;
+                self.patch(jump, self.here, &token)?;
+                self.flow.push(Flow::Else {
+                    jump: end_jump,
+                    token,
+                });
+            }
+            "end" => match self.flow.pop() {
+                Some(Flow::If { jump, .. } | Flow::Else { jump, .. }) => {
+                    self.patch(jump, self.here, &token)?
+                }
+                _ => return Err(token.error("end without an if ... begin")),
+            },
+            "loop" => self.flow.push(Flow::Loop {
+                start: self.here,
+                exits: Vec::new(),
+                token,
+            }),
+            "while" => {
+                self.condition(true)?;
+                let exit = self.here;
+                self.emit(0x1000, &token)?;
+                match self.flow.iter_mut().rev().find_map(|flow| match flow {
+                    Flow::Loop { exits, .. } => Some(exits),
+                    _ => None,
+                }) {
+                    Some(exits) => exits.push(exit),
+                    None => return Err(token.error("while outside a loop")),
+                }
+            }
+            "again" => match self.flow.pop() {
+                Some(Flow::Loop { start, exits, .. }) => {
+                    self.emit_addr(0x1000, Value::Known(start as i64), &token)?;
+                    for exit in exits {
+                        self.patch(exit, self.here, &token)?;
+                    }
+                }
+                _ => return Err(token.error("again without a loop")),
+            },
+            _ => {
+                if let Some(value) = self.constant(&token) {
+                    // numbers on their own are data, e.g. sprites
+                    let value = check_byte(value, &token)?;
+                    self.emit_byte(value, &token)?;
+                } else if self.macros.contains_key(&token.text) {
+                    self.expand(token)?;
+                } else if is_name(&token.text) {
+                    // anything else is a subroutine call
+                    self.emit_addr(0x2000, Value::Label(token.clone()), &token)?;
+                } else {
+                    return Err(token.error(format!("unexpected {}", token.text)));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn register_statement(&mut self, x: u8, token: Token) -> Result<(), AsmError> {
+        let op = self.next()?;
+        let vx = x_of(x);
+        if op.is(":=") {
+            let next = self.tokens.front().cloned();
+            match next {
+                Some(t) if t.is("random") => {
+                    self.next()?;
+                    let nn = self.byte()?;
+                    return self.emit(0xC000 | vx | nn as u16, &token);
+                }
+                Some(t) if t.is("delay") => {
+                    self.next()?;
+                    return self.emit(0xF007 | vx, &token);
+                }
+                Some(t) if t.is("key") => {
+                    self.next()?;
+                    return self.emit(0xF00A | vx, &token);
+                }
+                _ => {}
+            }
+        }
+
+        let register_op = match op.text.as_str() {
+            ":=" => Some(0x8000),
+            "|=" => Some(0x8001),
+            "&=" => Some(0x8002),
+            "^=" => Some(0x8003),
+            "+=" => Some(0x8004),
+            "-=" => Some(0x8005),
+            ">>=" => Some(0x8006),
+            "=-" => Some(0x8007),
+            "<<=" => Some(0x800E),
+            _ => None,
+        };
+        let Some(register_op) = register_op else {
+            return Err(op.error(format!("unexpected {} after {}", op.text, token.text)));
+        };
+        if let Some(y) = self.tokens.front().and_then(|t| self.register_of(t)) {
+            self.next()?;
+            return self.emit(register_op | vx | y_of(y), &token);
+        }
+        // only these three take a number
+        let nn = self.byte()?;
+        match op.text.as_str() {
+            ":=" => self.emit(0x6000 | vx | nn as u16, &token),
+            "+=" => self.emit(0x7000 | vx | nn as u16, &token),
+            "-=" => self.emit(0x7000 | vx | nn.wrapping_neg() as u16, &token),
+            _ => Err(op.error(format!("{} only works with registers", op.text))),
+        }
+    }
+
+    fn i_statement(&mut self, token: Token) -> Result<(), AsmError> {
+        let op = self.next()?;
+        match op.text.as_str() {
+            ":=" => {
+                if self.peek_is("long") {
+                    let long = self.next()?;
+                    self.require(Target::XoChip, &long)?;
+                    let value = self.value()?;
+                    self.emit(0xF000, &token)?;
+                    self.emit_long(value, &token)
+                } else if self.peek_is("hex") {
+                    self.next()?;
+                    let x = self.register()?;
+                    self.emit(0xF029 | x_of(x), &token)
+                } else if self.peek_is("bighex") {
+                    let bighex = self.next()?;
+                    self.require(Target::Schip, &bighex)?;
+                    let x = self.register()?;
+                    self.emit(0xF030 | x_of(x), &token)
+                } else {
+                    let value = self.value()?;
+                    self.emit_addr(0xA000, value, &token)
+                }
+            }
+            "+=" => {
+                let x = self.register()?;
+                self.emit(0xF01E | x_of(x), &token)
+            }
+            _ => Err(op.error(format!("unexpected {} after i", op.text))),
+        }
+    }
+
+    fn if_statement(&mut self, token: Token) -> Result<(), AsmError> {
+        // the condition's skip is worked out once it's known whether a block follows
+        let condition: Vec<Token> = self
+            .tokens
+            .iter()
+            .take_while(|t| !t.is("then") && !t.is("begin"))
+            .cloned()
+            .collect();
+        let block = self
+            .tokens
+            .get(condition.len())
+            .is_some_and(|t| t.is("begin"));
+        self.condition(block)?;
+        if block {
+            self.expect("begin")?;
+            let jump = self.here;
+            self.emit(0x1000, &token)?;
+            self.flow.push(Flow::If { jump, token });
+        } else {
+            self.expect("then")?;
+        }
+        Ok(())
+    }
+
+    // emits the instructions that skip the next one unless the condition holds, or if negated
+    // skip it when it does
+    fn condition(&mut self, negated: bool) -> Result<(), AsmError> {
+        let x = self.register()?;
+        let token = self.next()?;
+        let comparison = match (token.text.as_str(), negated) {
+            (c, false) => c,
+            ("==", true) => "!=",
+            ("!=", true) => "==",
+            ("key", true) => "-key",
+            ("-key", true) => "key",
+            ("<", true) => ">=",
+            (">", true) => "<=",
+            (">=", true) => "<",
+            ("<=", true) => ">",
+            (c, true) => c,
+        };
+        let vx = x_of(x);
+        match comparison {
+            "==" | "!=" => {
+                let equal = comparison == "==";
+                if let Some(y) = self.tokens.front().and_then(|t| self.register_of(t)) {
+                    self.next()?;
+                    let base = if equal { 0x9000 } else { 0x5000 };
+                    self.emit(base | vx | y_of(y), &token)
+                } else {
+                    let nn = self.byte()?;
+                    let base = if equal { 0x4000 } else { 0x3000 };
+                    self.emit(base | vx | nn as u16, &token)
+                }
+            }
+            "key" => self.emit(0xE0A1 | vx, &token),
+            "-key" => self.emit(0xE09E | vx, &token),
+            "<" | ">" | "<=" | ">=" => {
+                // compared by subtracting in VF, which is lost
+                if let Some(y) = self.tokens.front().and_then(|t| self.register_of(t)) {
+                    self.next()?;
+                    self.emit(0x8F00 | y_of(y), &token)?;
+                } else {
+                    let nn = self.byte()?;
+                    self.emit(0x6F00 | nn as u16, &token)?;
+                }
+                let subtract = if matches!(comparison, ">" | "<=") {
+                    0x8F05
+                } else {
+                    0x8F07
+                };
+                self.emit(subtract | y_of(x), &token)?;
+                let skip = if matches!(comparison, ">" | "<") {
+                    0x3F01
+                } else {
+                    0x4F01
+                };
+                self.emit(skip, &token)
+            }
+            _ => Err(token.error(format!("{} isn't a comparison", token.text))),
+        }
+    }
+
+    fn unpack(&mut self, token: &Token) -> Result<(), AsmError> {
+        // :unpack long label, or :unpack nibble label
+        let nibble = if self.peek_is("long") {
+            let long = self.next()?;
+            self.require(Target::XoChip, &long)?;
+            None
+        } else {
+            Some(self.nibble()?)
+        };
+        let value = self.value()?;
+        match value {
+            Value::Known(addr) => {
+                let addr = addr as u16;
+                let high = match nibble {
+                    Some(nibble) => nibble << 4 | (addr >> 8) as u8 & 0xF,
+                    None => (addr >> 8) as u8,
+                };
+                self.emit(0x6000 | high as u16, token)?;
+                self.emit(0x6100 | addr & 0xFF, token)
+            }
+            Value::Label(label) => {
+                self.fixups
+                    .push((self.here, Fixup::UnpackHigh(nibble), label.clone()));
+                self.emit(0x6000, token)?;
+                self.fixups.push((self.here, Fixup::UnpackLow, label));
+                self.emit(0x6100, token)
+            }
+        }
+    }
+
+    fn define_macro(&mut self) -> Result<(), AsmError> {
+        let name = self.name()?;
+        let mut params = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.is("{") {
+                break;
+            }
+            params.push(token.text);
+        }
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => break,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            body.push(token);
+        }
+        self.macros.insert(name.text, Macro { params, body });
+        Ok(())
+    }
+
+    // the body goes back in front of the rest of the source with each parameter swapped for the
+    // token it was given
+    fn expand(&mut self, token: Token) -> Result<(), AsmError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(token.error("too many macro expansions, does one expand itself?"));
+        }
+        let count = self.macros[&token.text].params.len();
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            args.push(self.next()?);
+        }
+        let m = &self.macros[&token.text];
+        let expanded: Vec<Token> = m
+            .body
+            .iter()
+            .map(|t| match m.params.iter().position(|p| *p == t.text) {
+                Some(i) => args[i].clone(),
+                None => t.clone(),
+            })
+            .collect();
+        for t in expanded.into_iter().rev() {
+            self.tokens.push_front(t);
+        }
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<Token, AsmError> {
+        let token = self.next()?;
+        if !is_name(&token.text) || self.register_of(&token).is_some() {
+            return Err(token.error(format!("{} can't be used as a name", token.text)));
+        }
+        Ok(token)
+    }
+
+    fn define(&mut self, name: Token, addr: usize) -> Result<(), AsmError> {
+        if self.labels.insert(name.text.clone(), addr as u16).is_some() {
+            return Err(name.error(format!("{} is already defined", name.text)));
+        }
+        Ok(())
+    }
+
+    fn register_of(&self, token: &Token) -> Option<u8> {
+        let text = token.text.as_str();
+        if let Some(&x) = self.aliases.get(text) {
+            return Some(x);
+        }
+        let digit = text.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn register(&mut self) -> Result<u8, AsmError> {
+        let token = self.next()?;
+        self.register_of(&token)
+            .ok_or_else(|| token.error(format!("expected a register but found {}", token.text)))
+    }
+
+    // numbers and constants, and labels that have already been defined
+    fn constant(&self, token: &Token) -> Option<i64> {
+        if let Some(value) = parse_number(&token.text) {
+            return Some(value);
+        }
+        if let Some(&value) = self.consts.get(&token.text) {
+            return Some(value.floor() as i64);
+        }
+        None
+    }
+
+    fn value(&mut self) -> Result<Value, AsmError> {
+        let token = self.next()?;
+        if token.is("{") {
+            return Ok(Value::Known(self.calc(&token)?.floor() as i64));
+        }
+        if let Some(value) = self.constant(&token) {
+            return Ok(Value::Known(value));
+        }
+        if let Some(&addr) = self.labels.get(&token.text) {
+            return Ok(Value::Known(addr as i64));
+        }
+        if is_name(&token.text) {
+            return Ok(Value::Label(token));
+        }
+        Err(token.error(format!("expected a value but found {}", token.text)))
+    }
+
+    // a value that has to be known already
+    fn known(&mut self) -> Result<i64, AsmError> {
+        match self.value()? {
+            Value::Known(value) => Ok(value),
+            Value::Label(token) => Err(token.error(format!(
+                "{} has to be defined before it's used here",
+                token.text
+            ))),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, AsmError> {
+        let token = self.tokens.front().cloned();
+        let value = self.known()?;
+        check_byte(value, token.as_ref().unwrap_or(&self.end))
+    }
+
+    fn nibble(&mut self) -> Result<u8, AsmError> {
+        let token = self.tokens.front().cloned();
+        let value = self.known()?;
+        if !(0..=0xF).contains(&value) {
+            let token = token.as_ref().unwrap_or(&self.end);
+            return Err(token.error(format!("{} doesn't fit in a nibble", value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn emit(&mut self, op: u16, token: &Token) -> Result<(), AsmError> {
+        let [high, low] = op.to_be_bytes();
+        self.emit_byte(high, token)?;
+        self.emit_byte(low, token)
+    }
+
+    fn emit_byte(&mut self, value: u8, token: &Token) -> Result<(), AsmError> {
+        if self.here >= self.rom.len() {
+            return Err(token.error("the program doesn't fit in memory"));
+        }
+        if self.used[self.here] {
+            return Err(token.error(format!("this overlaps what's already at {:#X}", self.here)));
+        }
+        self.rom[self.here] = value;
+        self.used[self.here] = true;
+        self.here += 1;
+        Ok(())
+    }
+
+    // an instruction with a 12 bit address
+    fn emit_addr(&mut self, base: u16, value: Value, token: &Token) -> Result<(), AsmError> {
+        match value {
+            Value::Known(addr) => {
+                let addr = check_addr(addr, token)?;
+                self.emit(base | addr, token)
+            }
+            Value::Label(label) => {
+                self.fixups.push((self.here, Fixup::Nnn, label.clone()));
+                self.emit(base, &label)
+            }
+        }
+    }
+
+    fn emit_long(&mut self, value: Value, token: &Token) -> Result<(), AsmError> {
+        match value {
+            Value::Known(addr) => {
+                if !(0..=0xFFFF).contains(&addr) {
+                    return Err(token.error(format!("{:#X} doesn't fit in 16 bits", addr)));
+                }
+                self.emit(addr as u16, token)
+            }
+            Value::Label(label) => {
+                self.fixups.push((self.here, Fixup::Long, label));
+                self.emit(0, token)
+            }
+        }
+    }
+
+    // points an already emitted jump at target
+    fn patch(&mut self, jump: usize, target: usize, token: &Token) -> Result<(), AsmError> {
+        let target = check_addr(target as i64, token)?;
+        self.rom[jump..jump + 2].copy_from_slice(&(0x1000 | target).to_be_bytes());
+        Ok(())
+    }
+
+    fn calc(&mut self, open: &Token) -> Result<f64, AsmError> {
+        let mut tokens = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.is("}") {
+                break;
+            }
+            tokens.push(token);
+        }
+        let mut pos = 0;
+        let value = self.expression(&tokens, &mut pos, open)?;
+        if let Some(token) = tokens.get(pos) {
+            return Err(token.error(format!("unexpected {} in expression", token.text)));
+        }
+        Ok(value)
+    }
+
+    fn expression(&self, tokens: &[Token], pos: &mut usize, open: &Token) -> Result<f64, AsmError> {
+        let left = self.term(tokens, pos, open)?;
+        let Some(op) = tokens.get(*pos).filter(|t| is_binary(&t.text)) else {
+            return Ok(left);
+        };
+        *pos += 1;
+        let right = self.expression(tokens, pos, open)?;
+        let (a, b) = (left, right);
+        let value = match op.text.as_str() {
+            "+" => a + b,
+            "-" => a - b,
+            "*" => a * b,
+            "/" | "%" if b == 0.0 => return Err(op.error("division by zero")),
+            "/" => a / b,
+            "%" => a % b,
+            "&" => (a as i64 & b as i64) as f64,
+            "|" => (a as i64 | b as i64) as f64,
+            "^" => (a as i64 ^ b as i64) as f64,
+            "<<" => ((a as i64) << (b as i64).clamp(0, 63)) as f64,
+            ">>" => ((a as i64) >> (b as i64).clamp(0, 63)) as f64,
+            "pow" => a.powf(b),
+            "min" => a.min(b),
+            "max" => a.max(b),
+            "<" => (a < b) as i64 as f64,
+            ">" => (a > b) as i64 as f64,
+            "<=" => (a <= b) as i64 as f64,
+            ">=" => (a >= b) as i64 as f64,
+            "==" => (a == b) as i64 as f64,
+            _ => (a != b) as i64 as f64,
+        };
+        Ok(value)
+    }
+
+    fn term(&self, tokens: &[Token], pos: &mut usize, open: &Token) -> Result<f64, AsmError> {
+        let token = tokens
+            .get(*pos)
+            .ok_or_else(|| open.error("the expression ends early"))?;
+        *pos += 1;
+        let value = match token.text.as_str() {
+            "(" => {
+                let value = self.expression(tokens, pos, open)?;
+                match tokens.get(*pos) {
+                    Some(t) if t.is(")") => *pos += 1,
+                    _ => return Err(token.error("( has no matching )")),
+                }
+                value
+            }
+            "-" => -self.term(tokens, pos, open)?,
+            "~" => !(self.term(tokens, pos, open)? as i64) as f64,
+            "!" => (self.term(tokens, pos, open)? == 0.0) as i64 as f64,
+            "@" => {
+                let addr = self.term(tokens, pos, open)? as usize;
+                self.rom.get(addr).copied().unwrap_or(0) as f64
+            }
+            "abs" => self.term(tokens, pos, open)?.abs(),
+            "sqrt" => self.term(tokens, pos, open)?.sqrt(),
+            "sin" => self.term(tokens, pos, open)?.sin(),
+            "cos" => self.term(tokens, pos, open)?.cos(),
+            "tan" => self.term(tokens, pos, open)?.tan(),
+            "exp" => self.term(tokens, pos, open)?.exp(),
+            "log" => self.term(tokens, pos, open)?.ln(),
+            "sign" => self.term(tokens, pos, open)?.signum(),
+            "floor" => self.term(tokens, pos, open)?.floor(),
+            "ceil" => self.term(tokens, pos, open)?.ceil(),
+            "HERE" => self.here as f64,
+            "PI" => std::f64::consts::PI,
+            "E" => std::f64::consts::E,
+            text => {
+                if let Some(value) = parse_number(text) {
+                    value as f64
+                } else if let Some(&value) = self.consts.get(text) {
+                    value
+                } else if let Some(&addr) = self.labels.get(text) {
+                    addr as f64
+                } else {
+                    return Err(token.error(format!(
+                        "{} has to be defined before it's used in an expression",
+                        text
+                    )));
+                }
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn x_of(x: u8) -> u16 {
+    (x as u16) << 8
+}
+
+fn y_of(y: u8) -> u16 {
+    (y as u16) << 4
+}
+
+fn is_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "+" | "-"
+            | "*"
+            | "/"
+            | "%"
+            | "&"
+            | "|"
+            | "^"
+            | "<<"
+            | ">>"
+            | "pow"
+            | "min"
+            | "max"
+            | "<"
+            | ">"
+            | "<="
+            | ">="
+            | "=="
+            | "!="
+    )
+}
+
+// Octo names can have dashes in, e.g. draw-player
+fn is_name(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// decimal, 0x hex or 0b binary, any of them negative
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or(digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b").or(digits.strip_prefix("0B")) {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value })
+}
+
+// bytes can be given signed, -1 is 0xFF
+fn check_byte(value: i64, token: &Token) -> Result<u8, AsmError> {
+    if !(-128..=255).contains(&value) {
+        return Err(token.error(format!("{} doesn't fit in a byte", value)));
+    }
+    Ok(value as u8)
+}
+
+fn check_addr(addr: i64, token: &Token) -> Result<u16, AsmError> {
+    if !(0..=0xFFF).contains(&addr) {
+        return Err(token.error(format!(
+            "{:#X} is past 0xFFF, use i := long to reach it",
+            addr
+        )));
+    }
+    Ok(addr as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(source: &str, target: Target) -> Vec<u8> {
+        match assemble(source, target) {
+            Ok(assembled) => assembled.rom,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn error(source: &str, target: Target) -> AsmError {
+        match assemble(source, target) {
+            Ok(assembled) => panic!("assembled to {:02X?}", assembled.rom),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn if_then() {
+        let rom = bytes("if v0 != 2 then v1 := 1", Target::Chip8);
+        assert_eq!(rom, [0x30, 0x02, 0x61, 0x01]);
+    }
+
+    #[test]
+    fn if_begin_else_end() {
+        let rom = bytes("if v0 == 1 begin v1 := 2 else v1 := 3 end", Target::Chip8);
+        // skip the jump to the else when v0 == 1, then jump over the else at the end
+        assert_eq!(
+            rom,
+            [0x30, 0x01, 0x12, 0x08, 0x61, 0x02, 0x12, 0x0A, 0x61, 0x03]
+        );
+    }
+
+    #[test]
+    fn comparisons_go_through_vf() {
+        let rom = bytes("if v1 < v2 then v0 := 1", Target::Chip8);
+        assert_eq!(rom, [0x8F, 0x20, 0x8F, 0x17, 0x3F, 0x01, 0x60, 0x01]);
+    }
+
+    #[test]
+    fn loop_while_again() {
+        let rom = bytes("loop v0 += 1 while v0 != 5 again", Target::Chip8);
+        assert_eq!(rom, [0x70, 0x01, 0x40, 0x05, 0x12, 0x08, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn unclosed_blocks() {
+        let e = error("v0 := 1\nloop v0 += 1", Target::Chip8);
+        assert_eq!((e.line, e.column), (2, 1));
+        assert_eq!(e.message, "loop has no matching again");
+        let e = error("end", Target::Chip8);
+        assert_eq!(e.message, "end without an if ... begin");
+    }
+
+    #[test]
+    fn next_points_at_the_operand() {
+        let assembled =
+            assemble("v0 := 1 :next target v1 := 2 i := target", Target::Chip8).expect("assembles");
+        assert_eq!(assembled.labels["target"], 0x203);
+        assert_eq!(assembled.rom, [0x60, 0x01, 0x61, 0x02, 0xA2, 0x03]);
+    }
+
+    #[test]
+    fn calc_is_right_to_left() {
+        let rom = bytes(
+            ":calc a { 10 - 2 - 3 }
+             :calc b { ( 10 - 2 ) - 3 }
+             :calc c { 2 * 3 + 1 }
+             :const d 7
+             :calc e { d << 1 }
+             :byte a :byte b :byte c :byte e",
+            Target::Chip8,
+        );
+        assert_eq!(rom, [11, 5, 8, 14]);
+    }
+
+    #[test]
+    fn calc_errors() {
+        let e = error(":calc a { 1 / 0 }", Target::Chip8);
+        assert_eq!(
+            (e.line, e.column, e.message.as_str()),
+            (1, 13, "division by zero")
+        );
+        let e = error(":calc a { later + 1 } : later", Target::Chip8);
+        assert_eq!(e.column, 11);
+    }
+
+    #[test]
+    fn macros_substitute_their_arguments() {
+        let rom = bytes(
+            ":macro twice r n { r += n r += n } twice v3 2",
+            Target::Chip8,
+        );
+        assert_eq!(rom, [0x73, 0x02, 0x73, 0x02]);
+    }
+
+    #[test]
+    fn macros_that_expand_themselves_stop() {
+        let e = error(":macro forever { forever } forever", Target::Chip8);
+        assert_eq!(
+            e.message,
+            "too many macro expansions, does one expand itself?"
+        );
+    }
+
+    #[test]
+    fn org_and_forward_references() {
+        let assembled =
+            assemble("jump later :org 0x208 : later clear", Target::Chip8).expect("assembles");
+        assert_eq!(assembled.rom, [0x12, 0x08, 0, 0, 0, 0, 0, 0, 0x00, 0xE0]);
+        assert_eq!(assembled.labels["later"], 0x208);
+    }
+
+    #[test]
+    fn overlapping_org() {
+        let e = error("clear :org 0x200 clear", Target::Chip8);
+        assert_eq!((e.line, e.column), (1, 18));
+        assert_eq!(e.message, "this overlaps what's already at 0x200");
+    }
+
+    #[test]
+    fn main_gets_a_jump_unless_it_is_first() {
+        let rom = bytes("clear : main jump main", Target::Chip8);
+        assert_eq!(rom, [0x12, 0x04, 0x00, 0xE0, 0x12, 0x04]);
+        let rom = bytes(": main jump main", Target::Chip8);
+        assert_eq!(rom, [0x12, 0x00]);
+    }
+
+    #[test]
+    fn sprite_data_and_calls() {
+        let rom = bytes(
+            ": main i := digit draw ; : draw sprite v0 v0 2 ; : digit 0xF0 -1",
+            Target::Chip8,
+        );
+        assert_eq!(
+            rom,
+            [0xA2, 0x0A, 0x22, 0x06, 0x00, 0xEE, 0xD0, 0x02, 0x00, 0xEE, 0xF0, 0xFF]
+        );
+    }
+
+    #[test]
+    fn targets_gate_instructions() {
+        let e = error("plane 1", Target::Schip);
+        assert_eq!((e.line, e.column), (1, 1));
+        assert_eq!(
+            e.message,
+            "plane needs XO-CHIP, this is being assembled for SUPER-CHIP"
+        );
+        let e = error("hires", Target::Chip8);
+        assert_eq!(
+            e.message,
+            "hires needs SUPER-CHIP, this is being assembled for CHIP-8"
+        );
+
+        assert_eq!(
+            bytes("hires scroll-down 3", Target::Schip),
+            [0x00, 0xFF, 0x00, 0xC3]
+        );
+        assert_eq!(
+            bytes("plane 3 i := long 0x1234 save v1 - v3", Target::XoChip),
+            [0xF3, 0x01, 0xF0, 0x00, 0x12, 0x34, 0x51, 0x32]
+        );
+    }
+
+    #[test]
+    fn errors_have_line_and_column() {
+        let e = error("v0 := 1\n  v1 := 300", Target::Chip8);
+        assert_eq!((e.line, e.column), (2, 9));
+        assert_eq!(e.to_string(), "2:9: 300 doesn't fit in a byte");
+
+        let e = error("# comment\njump nowhere", Target::Chip8);
+        assert_eq!((e.line, e.column), (2, 6));
+        assert_eq!(e.message, "nowhere isn't defined");
+
+        let e = error("v0 :=", Target::Chip8);
+        assert_eq!(e.message, "the file ends partway through a statement");
+    }
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+// behaviours that differ between chip-8 interpreters. ROMs were written against whichever one
+// their author had, so many only run correctly with the matching set. The defaults are what this
+// emulator has always done
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    // 8XY1, 8XY2 and 8XY3 clear VF
+    pub vf_reset: bool,
+    // FX55 and FX65 leave I pointing after the last register copied
+    pub memory_increment: bool,
+    // sprites are cut off at the screen edges instead of wrapping around
+    pub clipping: bool,
+    // 8XY6 and 8XYE shift VY into VX instead of shifting VX in place
+    pub shift_vy: bool,
+    // BNNN jumps to VX + NNN, X being the top nibble of NNN, instead of V0 + NNN
+    pub jump_vx: bool,
+}
+
+impl Quirks {
+    pub const PROFILES: [&'static str; 4] = ["modern", "vip", "schip", "xochip"];
+
+    // what most modern interpreters and tutorials do
+    pub fn modern() -> Self {
+        Self::default()
+    }
+
+    // the original COSMAC VIP interpreter
+    pub fn vip() -> Self {
+        Self {
+            vf_reset: true,
+            memory_increment: true,
+            clipping: true,
+            shift_vy: true,
+            jump_vx: false,
+        }
+    }
+
+    // SUPER-CHIP 1.1 on the HP48
+    pub fn schip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: false,
+            clipping: true,
+            shift_vy: false,
+            jump_vx: true,
+        }
+    }
+
+    // XO-CHIP as implemented by Octo
+    pub fn xochip() -> Self {
+        Self {
+            vf_reset: false,
+            memory_increment: true,
+            clipping: false,
+            shift_vy: true,
+            jump_vx: false,
+        }
+    }
+
+    pub fn profile(name: &str) -> Option<Self> {
+        match name {
+            "modern" => Some(Self::modern()),
+            "vip" | "chip8" | "chip-8" => Some(Self::vip()),
+            "schip" | "superchip" => Some(Self::schip()),
+            "xochip" | "xo-chip" => Some(Self::xochip()),
+            _ => None,
+        }
+    }
+
+    // the name of the profile these quirks match, if any
+    pub fn profile_name(&self) -> Option<&'static str> {
+        Self::PROFILES
+            .into_iter()
+            .find(|name| Self::profile(name) == Some(*self))
+    }
+}
@@ -0,0 +1,230 @@
+// turns the display into an RGBA8 buffer that every frontend can hand straight to its texture,
+// canvas or image encoder
+
+pub type Rgba = [u8; 4];
+
+pub const NUM_COLORS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Palette {
+    // index 0 is the background, 1 the foreground and 2 and 3 are only used by multi-plane modes
+    pub colors: [Rgba; NUM_COLORS],
+}
+
+impl Palette {
+    pub const PRESETS: [&'static str; 4] = ["classic", "amber", "green", "high-contrast"];
+
+    pub fn new(background: Rgba, foreground: Rgba) -> Self {
+        Self {
+            colors: [
+                background,
+                foreground,
+                [0xAA, 0xAA, 0xAA, 0xFF],
+                [0x55, 0x55, 0x55, 0xFF],
+            ],
+        }
+    }
+
+    pub fn classic() -> Self {
+        Self::new([0x00, 0x00, 0x00, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF])
+    }
+
+    pub fn amber() -> Self {
+        Self {
+            colors: [
+                [0x1A, 0x0F, 0x00, 0xFF],
+                [0xFF, 0xB0, 0x00, 0xFF],
+                [0xB3, 0x6B, 0x00, 0xFF],
+                [0x66, 0x3D, 0x00, 0xFF],
+            ],
+        }
+    }
+
+    pub fn green() -> Self {
+        Self {
+            colors: [
+                [0x00, 0x14, 0x00, 0xFF],
+                [0x33, 0xFF, 0x33, 0xFF],
+                [0x1F, 0x99, 0x1F, 0xFF],
+                [0x0F, 0x4D, 0x0F, 0xFF],
+            ],
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            colors: [
+                [0x00, 0x00, 0x00, 0xFF],
+                [0xFF, 0xFF, 0x00, 0xFF],
+                [0x00, 0xFF, 0xFF, 0xFF],
+                [0xFF, 0x00, 0xFF, 0xFF],
+            ],
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "classic" => Some(Self::classic()),
+            "amber" => Some(Self::amber()),
+            "green" | "green-phosphor" => Some(Self::green()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    pub fn background(&self) -> Rgba {
+        self.colors[0]
+    }
+
+    pub fn foreground(&self) -> Rgba {
+        self.colors[1]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+// at 1.0 a pixel would never fade, so the screen would fill up for good
+pub const MAX_PERSISTENCE: f32 = 0.99;
+
+pub struct Renderer {
+    pub palette: Palette,
+    scale: usize,
+    grid: Option<Rgba>,
+    persistence: f32,
+    width: usize,
+    height: usize,
+    buffer: Vec<u8>,
+    // colour each display pixel was last shown with, used for the persistence blend
+    glow: Vec<[f32; 4]>,
+}
+
+impl Renderer {
+    pub fn new(palette: Palette) -> Self {
+        Self {
+            palette,
+            scale: 1,
+            grid: None,
+            persistence: 0.0,
+            width: 0,
+            height: 0,
+            buffer: Vec::new(),
+            glow: Vec::new(),
+        }
+    }
+
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: usize) {
+        self.scale = scale.max(1);
+    }
+
+    // draws the last row and column of every scaled pixel in this colour, needs a scale of 2 or
+    // more to show up
+    pub fn set_grid(&mut self, grid: Option<Rgba>) {
+        self.grid = grid;
+    }
+
+    // how much of the previous frame survives when a pixel turns off, from 0.0 (none) up to
+    // MAX_PERSISTENCE. Something around 0.6 hides most of the flicker from games that erase and
+    // redraw sprites
+    pub fn set_persistence(&mut self, persistence: f32) {
+        self.persistence = if persistence.is_nan() {
+            0.0
+        } else {
+            persistence.clamp(0.0, MAX_PERSISTENCE)
+        };
+        if self.persistence == 0.0 {
+            self.glow.clear();
+        }
+    }
+
+    // size of the output in pixels
+    pub fn width(&self) -> usize {
+        self.width * self.scale
+    }
+
+    pub fn height(&self) -> usize {
+        self.height * self.scale
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    // pixels are colour indices into the palette, so a bool display picks between background
+    // and foreground and a multi-plane display can use all four colours
+    pub fn render<P>(&mut self, pixels: &[P], width: usize, height: usize)
+    where
+        P: Copy + Into<usize>,
+    {
+        assert_eq!(pixels.len(), width * height, "display size mismatch");
+
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.glow.clear();
+        }
+
+        let out_width = self.width();
+        self.buffer.resize(out_width * self.height() * 4, 0);
+        if self.persistence > 0.0 && self.glow.len() != pixels.len() {
+            self.glow = pixels
+                .iter()
+                .map(|&p| to_float(self.palette.colors[p.into() % NUM_COLORS]))
+                .collect();
+        }
+
+        for (idx, &pixel) in pixels.iter().enumerate() {
+            let color_idx = pixel.into() % NUM_COLORS;
+            let color = if self.persistence > 0.0 {
+                self.blend(idx, color_idx)
+            } else {
+                self.palette.colors[color_idx]
+            };
+
+            let x = (idx % width) * self.scale;
+            let y = (idx / width) * self.scale;
+            for dy in 0..self.scale {
+                let row = (y + dy) * out_width;
+                for dx in 0..self.scale {
+                    let on_grid = self.scale > 1 && (dx == self.scale - 1 || dy == self.scale - 1);
+                    let color = match self.grid {
+                        Some(grid) if on_grid => grid,
+                        _ => color,
+                    };
+                    let offset = (row + x + dx) * 4;
+                    self.buffer[offset..offset + 4].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+
+    fn blend(&mut self, idx: usize, color_idx: usize) -> Rgba {
+        let target = to_float(self.palette.colors[color_idx]);
+        let glow = &mut self.glow[idx];
+        if color_idx != 0 {
+            // lit pixels show up straight away, only turning off fades
+            *glow = target;
+        } else {
+            for c in 0..4 {
+                glow[c] = glow[c] * self.persistence + target[c] * (1.0 - self.persistence);
+            }
+        }
+        [glow[0] as u8, glow[1] as u8, glow[2] as u8, glow[3] as u8]
+    }
+}
+
+fn to_float(color: Rgba) -> [f32; 4] {
+    [
+        color[0] as f32,
+        color[1] as f32,
+        color[2] as f32,
+        color[3] as f32,
+    ]
+}
@@ -0,0 +1,163 @@
+// what's known about roms, looked up by rom_hash. Reads the programs.json from the CHIP-8
+// community database (github.com/chip-8/chip-8-database), which lists programs with the SHA-1 of
+// every released version of each
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{rom_hash, Quirks};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RomInfo {
+    pub title: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub release: Option<String>,
+    // platform ids in the database's spelling, best fit first, e.g. "originalChip8"
+    pub platforms: Vec<String>,
+    // instructions per frame the rom was made for
+    pub tickrate: Option<usize>,
+}
+
+impl RomInfo {
+    pub fn platform(&self) -> Option<&str> {
+        self.platforms.first().map(String::as_str)
+    }
+
+    // the quirks of the rom's preferred platform
+    pub fn quirks(&self) -> Option<Quirks> {
+        self.platforms.iter().find_map(|id| platform_quirks(id))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RomDb {
+    roms: HashMap<String, RomInfo>,
+}
+
+#[derive(Debug)]
+pub enum RomDbError {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for RomDbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomDbError::Io(e) => write!(f, "{}", e),
+            RomDbError::Json(e) => write!(f, "not a rom database: {}", e),
+        }
+    }
+}
+
+impl Error for RomDbError {}
+
+// the parts of programs.json that are used, everything else is ignored
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    release: Option<String>,
+    #[serde(default)]
+    roms: HashMap<String, Rom>,
+}
+
+#[derive(Deserialize)]
+struct Rom {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    release: Option<String>,
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    tickrate: Option<usize>,
+}
+
+impl RomDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_json(s: &str) -> Result<Self, RomDbError> {
+        let programs: Vec<Program> = serde_json::from_str(s).map_err(RomDbError::Json)?;
+        let mut roms = HashMap::new();
+        for program in programs {
+            for (hash, rom) in program.roms {
+                // a version's own description and release date are more specific than the
+                // program's
+                let info = RomInfo {
+                    title: program.title.clone(),
+                    description: rom.description.or_else(|| program.description.clone()),
+                    authors: program.authors.clone(),
+                    release: rom.release.or_else(|| program.release.clone()),
+                    platforms: rom.platforms,
+                    tickrate: rom.tickrate,
+                };
+                roms.insert(hash.to_ascii_lowercase(), info);
+            }
+        }
+        Ok(Self { roms })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, RomDbError> {
+        let contents = fs::read_to_string(path).map_err(RomDbError::Io)?;
+        Self::from_json(&contents)
+    }
+
+    pub fn len(&self) -> usize {
+        self.roms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&RomInfo> {
+        self.roms.get(hash)
+    }
+
+    pub fn lookup(&self, rom: &[u8]) -> Option<&RomInfo> {
+        self.get(&rom_hash(rom))
+    }
+
+    pub fn insert(&mut self, hash: &str, info: RomInfo) {
+        self.roms.insert(hash.to_ascii_lowercase(), info);
+    }
+}
+
+// a readable name for one of the database's platform ids
+pub fn platform_name(id: &str) -> &str {
+    match id {
+        "originalChip8" => "CHIP-8",
+        "hybridVIP" => "CHIP-8 hybrid",
+        "modernChip8" => "Modern CHIP-8",
+        "chip8x" => "CHIP-8X",
+        "chip48" => "CHIP-48",
+        "superchip1" => "SUPER-CHIP 1.0",
+        "superchip" => "SUPER-CHIP 1.1",
+        "megachip8" => "MEGA-CHIP",
+        "xochip" => "XO-CHIP",
+        _ => id,
+    }
+}
+
+// the closest quirk profile for a platform id
+pub fn platform_quirks(id: &str) -> Option<Quirks> {
+    match id {
+        "originalChip8" | "hybridVIP" | "chip8x" => Some(Quirks::vip()),
+        "modernChip8" => Some(Quirks::modern()),
+        "chip48" | "superchip1" | "superchip" => Some(Quirks::schip()),
+        "xochip" => Some(Quirks::xochip()),
+        _ => None,
+    }
+}
@@ -0,0 +1,302 @@
+// runs a machine on a thread of its own at 60 frames a second, so nothing a frontend does
+// (resizing windows, file dialogs, waiting on vsync) can disturb emulation timing. Commands go in
+// over one channel and every finished frame, picture and sound included, comes back over another
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::audio::{Buzzer, FRAMES_PER_SECOND};
+use crate::{Emu, Fault, LoadError, SaveState, StateError};
+
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND as u64);
+
+// what a Runner drives, an Emu and whatever the frontend keeps alongside it
+pub trait Machine: Send + 'static {
+    // anything a frame has to tell the frontend besides the picture and the sound
+    type Report: Send + 'static;
+
+    fn emu(&self) -> &Emu;
+    fn emu_mut(&mut self) -> &mut Emu;
+    // runs one 60Hz frame, or nothing if paused
+    fn frame(&mut self) -> Self::Report;
+    fn set_paused(&mut self, paused: bool);
+
+    // whether the buzzer sounds for the frame just run
+    fn is_sounding(&self) -> bool {
+        self.emu().is_sounding()
+    }
+
+    // called after a command replaces the emu's memory and registers, for machines that keep
+    // their own view of them
+    fn reloaded(&mut self) {}
+}
+
+// an Emu on its own at a fixed speed
+pub struct Basic {
+    pub emu: Emu,
+    pub ticks_per_frame: usize,
+    pub paused: bool,
+}
+
+impl Basic {
+    pub fn new(emu: Emu, ticks_per_frame: usize) -> Self {
+        Self {
+            emu,
+            ticks_per_frame,
+            paused: false,
+        }
+    }
+}
+
+impl Machine for Basic {
+    // the fault that stopped it, if any
+    type Report = Option<Fault>;
+
+    fn emu(&self) -> &Emu {
+        &self.emu
+    }
+
+    fn emu_mut(&mut self) -> &mut Emu {
+        &mut self.emu
+    }
+
+    fn frame(&mut self) -> Option<Fault> {
+        if !self.paused {
+            self.emu.run_frame(self.ticks_per_frame);
+        }
+        self.emu.fault()
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    fn is_sounding(&self) -> bool {
+        self.emu.is_sounding() && !self.paused
+    }
+}
+
+pub enum Command<M> {
+    Key { key: usize, pressed: bool },
+    Pause(bool),
+    // resets the emu and loads a rom
+    Load(Vec<u8>),
+    LoadState(Box<SaveState>),
+    // anything else, run between frames
+    Run(Box<dyn FnOnce(&mut M) + Send>),
+}
+
+enum Message<M> {
+    Command(Command<M>),
+    Quit,
+}
+
+pub struct Frame<R> {
+    // counts up from 0
+    pub number: u64,
+    // row by row, width * height pixels
+    pub display: Vec<bool>,
+    pub width: usize,
+    pub height: usize,
+    pub sounding: bool,
+    // the buzzer for this frame at the runner's sample rate, silence included, so queueing every
+    // frame's samples keeps the sound in step
+    pub samples: Vec<f32>,
+    pub report: R,
+    // commands that failed since the last frame
+    pub errors: Vec<RunnerError>,
+}
+
+#[derive(Debug)]
+pub enum RunnerError {
+    // the emulation thread is gone, it only stops on its own if the machine panicked
+    Stopped,
+    Load(LoadError),
+    State(StateError),
+    // a key past 0xF
+    Key(usize),
+}
+
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunnerError::Stopped => write!(f, "the emulation thread has stopped"),
+            RunnerError::Load(e) => write!(f, "{}", e),
+            RunnerError::State(e) => write!(f, "{}", e),
+            RunnerError::Key(key) => write!(f, "there's no key {:#X}, they go up to 0xF", key),
+        }
+    }
+}
+
+impl Error for RunnerError {}
+
+pub struct Runner<M: Machine> {
+    commands: Sender<Message<M>>,
+    frames: Receiver<Frame<M::Report>>,
+    thread: Option<JoinHandle<M>>,
+}
+
+impl<M: Machine> Runner<M> {
+    // the samples in each frame are made at sample_rate, which should be whatever the audio
+    // device ended up with
+    pub fn spawn(machine: M, sample_rate: u32) -> io::Result<Self> {
+        let (commands, command_rx) = mpsc::channel();
+        let (frame_tx, frames) = mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("chip8-emulation".to_string())
+            .spawn(move || run(machine, sample_rate, command_rx, frame_tx))?;
+        Ok(Self {
+            commands,
+            frames,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn send(&self, command: Command<M>) -> Result<(), RunnerError> {
+        self.commands
+            .send(Message::Command(command))
+            .map_err(|_| RunnerError::Stopped)
+    }
+
+    pub fn run<F>(&self, f: F) -> Result<(), RunnerError>
+    where
+        F: FnOnce(&mut M) + Send + 'static,
+    {
+        self.send(Command::Run(Box::new(f)))
+    }
+
+    // runs f on the emulation thread and waits for what it returns. Commands are picked up
+    // between frames as soon as they arrive, so this waits for a frame at most
+    pub fn call<F, R>(&self, f: F) -> Result<R, RunnerError>
+    where
+        F: FnOnce(&mut M) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.run(move |machine| {
+            tx.send(f(machine)).ok();
+        })?;
+        rx.recv().map_err(|_| RunnerError::Stopped)
+    }
+
+    pub fn save_state(&self) -> Result<SaveState, RunnerError> {
+        self.call(|machine| machine.emu().save_state())
+    }
+
+    // waits up to timeout for a frame, then takes every other one that's ready too. Empty if
+    // none came in time
+    pub fn recv_frames(&self, timeout: Duration) -> Result<Vec<Frame<M::Report>>, RunnerError> {
+        let first = match self.frames.recv_timeout(timeout) {
+            Ok(frame) => frame,
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => return Err(RunnerError::Stopped),
+        };
+        let mut frames = vec![first];
+        frames.extend(self.frames.try_iter());
+        Ok(frames)
+    }
+
+    // ends the thread and hands back the machine, or None if it panicked
+    pub fn stop(mut self) -> Option<M> {
+        self.quit()
+    }
+
+    fn quit(&mut self) -> Option<M> {
+        self.commands.send(Message::Quit).ok();
+        self.thread.take()?.join().ok()
+    }
+}
+
+impl<M: Machine> Drop for Runner<M> {
+    fn drop(&mut self) {
+        self.quit();
+    }
+}
+
+fn run<M: Machine>(
+    mut machine: M,
+    sample_rate: u32,
+    commands: Receiver<Message<M>>,
+    frames: Sender<Frame<M::Report>>,
+) -> M {
+    let mut buzzer = Buzzer::new(sample_rate);
+    let mut number = 0;
+    let mut errors = Vec::new();
+    let mut next_frame = Instant::now();
+    loop {
+        // handle commands as they come in until the next frame is due
+        loop {
+            let timeout = next_frame.saturating_duration_since(Instant::now());
+            match commands.recv_timeout(timeout) {
+                Ok(Message::Command(command)) => {
+                    if let Err(e) = apply(&mut machine, command) {
+                        errors.push(e);
+                    }
+                }
+                Ok(Message::Quit) | Err(RecvTimeoutError::Disconnected) => return machine,
+                Err(RecvTimeoutError::Timeout) => break,
+            }
+        }
+        // don't try to catch up after the thread was suspended or stalled
+        let now = Instant::now();
+        next_frame = if now > next_frame + FRAME_TIME {
+            now + FRAME_TIME
+        } else {
+            next_frame + FRAME_TIME
+        };
+
+        let report = machine.frame();
+        let sounding = machine.is_sounding();
+        let mut samples = vec![0.0; buzzer.samples_per_frame()];
+        buzzer.fill(sounding, &mut samples);
+        let emu = machine.emu();
+        let (width, height) = emu.display_size();
+        let frame = Frame {
+            number,
+            display: emu.get_display().to_vec(),
+            width,
+            height,
+            sounding,
+            samples,
+            report,
+            errors: std::mem::take(&mut errors),
+        };
+        number += 1;
+        // nobody's listening any more
+        if frames.send(frame).is_err() {
+            return machine;
+        }
+    }
+}
+
+fn apply<M: Machine>(machine: &mut M, command: Command<M>) -> Result<(), RunnerError> {
+    match command {
+        Command::Key { key, pressed } => {
+            let emu = machine.emu_mut();
+            if key >= emu.keys().len() {
+                return Err(RunnerError::Key(key));
+            }
+            emu.keypress(key, pressed);
+        }
+        Command::Pause(paused) => machine.set_paused(paused),
+        Command::Load(rom) => {
+            let emu = machine.emu_mut();
+            emu.reset();
+            emu.load(&rom).map_err(RunnerError::Load)?;
+            machine.reloaded();
+        }
+        Command::LoadState(state) => {
+            machine
+                .emu_mut()
+                .load_state(&state)
+                .map_err(RunnerError::State)?;
+            machine.reloaded();
+        }
+        Command::Run(f) => f(machine),
+    }
+    Ok(())
+}
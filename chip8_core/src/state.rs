@@ -0,0 +1,263 @@
+// snapshots of everything an Emu needs to carry on from where it was, along with a small binary
+// format for writing them to disk. The keypad isn't saved since it belongs to whoever is playing
+
+use std::error::Error;
+use std::fmt;
+
+use crate::{
+    ConfigError, Emu, Event, MachineConfig, Quirks, HIRES_HEIGHT, HIRES_WIDTH, NUM_REGS,
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+const MAGIC: &[u8; 4] = b"C8ST";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveState {
+    pub config: MachineConfig,
+    // saved so a rom carries on behaving the same whatever profile it's restored under
+    pub quirks: Quirks,
+    pub pc: u16,
+    pub i_reg: u16,
+    pub sp: u16,
+    pub dt: u8,
+    pub st: u8,
+    pub waiting_for_key: bool,
+    pub v_reg: [u8; NUM_REGS],
+    pub stack: Vec<u16>,
+    pub ram: Vec<u8>,
+    pub hires: bool,
+    pub screen: Vec<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateError {
+    BadMagic,
+    UnsupportedVersion(u8),
+    Truncated,
+    InvalidConfig(ConfigError),
+    // the sizes recorded in the state don't match its contents
+    Corrupt,
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::BadMagic => write!(f, "not a save state"),
+            StateError::UnsupportedVersion(v) => write!(f, "unsupported save state version {}", v),
+            StateError::Truncated => write!(f, "save state is truncated"),
+            StateError::InvalidConfig(e) => write!(f, "save state has an invalid config: {}", e),
+            StateError::Corrupt => write!(f, "save state is corrupt"),
+        }
+    }
+}
+
+impl Error for StateError {}
+
+impl SaveState {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.ram.len() + self.screen.len() + 64);
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend_from_slice(&(self.config.ram_size as u32).to_le_bytes());
+        out.extend_from_slice(&self.config.start_addr.to_le_bytes());
+        out.extend_from_slice(&(self.config.stack_depth as u32).to_le_bytes());
+        out.push(quirks_to_bits(self.quirks));
+        out.extend_from_slice(&self.pc.to_le_bytes());
+        out.extend_from_slice(&self.i_reg.to_le_bytes());
+        out.extend_from_slice(&self.sp.to_le_bytes());
+        out.push(self.dt);
+        out.push(self.st);
+        out.push(self.waiting_for_key as u8);
+        out.extend_from_slice(&self.v_reg);
+        for addr in &self.stack {
+            out.extend_from_slice(&addr.to_le_bytes());
+        }
+        out.extend_from_slice(&self.ram);
+        out.push(self.hires as u8);
+        out.extend(self.screen.iter().map(|&p| p as u8));
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StateError> {
+        let mut reader = Reader { data, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u8()?;
+        if version != VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+
+        let config = MachineConfig {
+            ram_size: reader.u32()? as usize,
+            start_addr: reader.u16()?,
+            stack_depth: reader.u32()? as usize,
+        };
+        config.validate().map_err(StateError::InvalidConfig)?;
+        let quirks = quirks_from_bits(reader.u8()?);
+
+        let pc = reader.u16()?;
+        let i_reg = reader.u16()?;
+        let sp = reader.u16()?;
+        let dt = reader.u8()?;
+        let st = reader.u8()?;
+        let waiting_for_key = reader.u8()? != 0;
+        let mut v_reg = [0; NUM_REGS];
+        v_reg.copy_from_slice(reader.take(NUM_REGS)?);
+        let stack = (0..config.stack_depth)
+            .map(|_| reader.u16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let ram = reader.take(config.ram_size)?.to_vec();
+        let hires = reader.u8()? != 0;
+        let screen = reader
+            .take(screen_len(hires))?
+            .iter()
+            .map(|&p| p != 0)
+            .collect();
+
+        if sp as usize > config.stack_depth || reader.pos != data.len() {
+            return Err(StateError::Corrupt);
+        }
+
+        Ok(Self {
+            config,
+            quirks,
+            pc,
+            i_reg,
+            sp,
+            dt,
+            st,
+            waiting_for_key,
+            v_reg,
+            stack,
+            ram,
+            hires,
+            screen,
+        })
+    }
+}
+
+// one bit each, in the order they're declared
+fn quirks_to_bits(quirks: Quirks) -> u8 {
+    [
+        quirks.vf_reset,
+        quirks.memory_increment,
+        quirks.clipping,
+        quirks.shift_vy,
+        quirks.jump_vx,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, &on)| bits | (on as u8) << i)
+}
+
+fn quirks_from_bits(bits: u8) -> Quirks {
+    let on = |i: u8| bits & 1 << i != 0;
+    Quirks {
+        vf_reset: on(0),
+        memory_increment: on(1),
+        clipping: on(2),
+        shift_vy: on(3),
+        jump_vx: on(4),
+    }
+}
+
+fn screen_len(hires: bool) -> usize {
+    if hires {
+        HIRES_WIDTH * HIRES_HEIGHT
+    } else {
+        SCREEN_WIDTH * SCREEN_HEIGHT
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(StateError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, StateError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, StateError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+impl Emu {
+    pub fn save_state(&self) -> SaveState {
+        SaveState {
+            config: self.config,
+            quirks: self.quirks,
+            pc: self.pc,
+            i_reg: self.i_reg,
+            sp: self.sp,
+            dt: self.dt,
+            st: self.st,
+            waiting_for_key: self.waiting_for_key,
+            v_reg: self.v_reg,
+            stack: self.stack.clone(),
+            ram: self.ram.clone(),
+            hires: self.hires,
+            screen: self.screen.clone(),
+        }
+    }
+
+    // the machine takes on the state's config and quirks, so a state saved with a different
+    // layout or profile still restores as it was saved. Observers stay subscribed
+    pub fn load_state(&mut self, state: &SaveState) -> Result<(), StateError> {
+        state.config.validate().map_err(StateError::InvalidConfig)?;
+        if state.ram.len() != state.config.ram_size
+            || state.stack.len() != state.config.stack_depth
+            || state.screen.len() != screen_len(state.hires)
+            || state.sp as usize > state.config.stack_depth
+        {
+            return Err(StateError::Corrupt);
+        }
+
+        let was_hires = self.hires;
+        let was_sounding = self.st > 0;
+        self.config = state.config;
+        self.quirks = state.quirks;
+        self.pc = state.pc;
+        self.i_reg = state.i_reg;
+        self.sp = state.sp;
+        self.dt = state.dt;
+        self.st = state.st;
+        self.waiting_for_key = state.waiting_for_key;
+        self.v_reg = state.v_reg;
+        self.stack = state.stack.clone();
+        self.ram = state.ram.clone();
+        self.hires = state.hires;
+        self.screen = state.screen.clone();
+        self.fault = None;
+
+        if self.hires != was_hires {
+            self.emit(Event::ResolutionChanged { hires: self.hires });
+        }
+        match (was_sounding, self.st > 0) {
+            (false, true) => self.emit(Event::SoundStarted),
+            (true, false) => self.emit(Event::SoundStopped),
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
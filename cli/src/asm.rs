@@ -0,0 +1,66 @@
+use chip8_core::asm::{self, Assembled};
+use chip8_core::octo::{self, Target};
+use clap::Args;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct AsmArgs {
+    source: PathBuf,
+    /// Where to write the rom, the source with a .ch8 extension by default
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Read the source as Octo, the default for .8o files
+    #[arg(long)]
+    octo: bool,
+    /// Instruction set for Octo sources, one of chip8, schip or xochip
+    #[arg(long, default_value = "xochip")]
+    target: String,
+    /// Also write every label and its address to this file
+    #[arg(long)]
+    symbols: Option<PathBuf>,
+}
+
+pub fn run(args: AsmArgs) -> Result<(), Box<dyn Error>> {
+    let source = fs::read_to_string(&args.source)
+        .map_err(|e| format!("{}: {}", args.source.display(), e))?;
+    let octo = args.octo || args.source.extension().is_some_and(|ext| ext == "8o");
+    let assembled = if octo {
+        let target = Target::from_name(&args.target).ok_or_else(|| {
+            format!(
+                "unknown target {}, expected one of {}",
+                args.target,
+                Target::NAMES.join(", ")
+            )
+        })?;
+        octo::assemble(&source, target)
+    } else {
+        asm::assemble(&source)
+    }
+    .map_err(|e| format!("{}:{}", args.source.display(), e))?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| args.source.with_extension("ch8"));
+    fs::write(&output, &assembled.rom).map_err(|e| format!("{}: {}", output.display(), e))?;
+    println!("{}: {} bytes", output.display(), assembled.rom.len());
+    if let Some(path) = &args.symbols {
+        fs::write(path, symbol_map(&assembled))
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!("{}: {} labels", path.display(), assembled.labels.len());
+    }
+    Ok(())
+}
+
+// one label per line in address order
+fn symbol_map(assembled: &Assembled) -> String {
+    let mut labels: Vec<(&String, &u16)> = assembled.labels.iter().collect();
+    labels.sort_by_key(|&(name, &addr)| (addr, name));
+    let mut out = String::new();
+    for (name, addr) in labels {
+        let _ = writeln!(out, "{:04X} {}", addr, name);
+    }
+    out
+}
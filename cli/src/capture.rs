@@ -0,0 +1,147 @@
+use crate::machine::MachineArgs;
+use chip8_core::audio::Buzzer;
+use chip8_core::capture::{self, GifRecorder, WavWriter, Y4mWriter};
+use chip8_core::render::{Palette, Renderer};
+use chip8_core::{Emu, HIRES_HEIGHT, HIRES_WIDTH};
+use clap::Args;
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+#[derive(Args)]
+pub struct CaptureArgs {
+    #[command(flatten)]
+    machine: MachineArgs,
+    /// Number of 60Hz frames to run
+    #[arg(long, default_value_t = 600)]
+    frames: usize,
+    /// Pixels per lo-res pixel. Recordings are made on the hi-res grid at half this, rounded up,
+    /// so they keep one size if the rom switches resolution
+    #[arg(long, default_value_t = 10)]
+    scale: usize,
+    /// One of classic, amber, green or high-contrast
+    #[arg(long, default_value = "classic")]
+    palette: String,
+    /// PNG of the last frame
+    #[arg(long)]
+    png: Option<PathBuf>,
+    /// SVG of the last frame
+    #[arg(long)]
+    svg: Option<PathBuf>,
+    /// Animated GIF of every frame
+    #[arg(long)]
+    gif: Option<PathBuf>,
+    /// Raw Y4M video of every frame
+    #[arg(long)]
+    video: Option<PathBuf>,
+    /// WAV of the buzzer, lines up with --video
+    #[arg(long)]
+    wav: Option<PathBuf>,
+}
+
+pub fn run(args: CaptureArgs) -> Result<(), Box<dyn Error>> {
+    let palette = Palette::preset(&args.palette)
+        .ok_or_else(|| format!("unknown palette {}", args.palette))?;
+    let mut renderer = Renderer::new(palette);
+    let record_scale = args.scale.div_ceil(2).max(1);
+    renderer.set_scale(record_scale);
+
+    let mut emu = args.machine.load()?;
+
+    let mut gif = match &args.gif {
+        Some(path) => Some(GifRecorder::new(
+            BufWriter::new(File::create(path)?),
+            HIRES_WIDTH,
+            HIRES_HEIGHT,
+            record_scale,
+            &palette,
+        )?),
+        None => None,
+    };
+    let mut video = match &args.video {
+        Some(path) => Some(Y4mWriter::new(
+            BufWriter::new(File::create(path)?),
+            HIRES_WIDTH * record_scale,
+            HIRES_HEIGHT * record_scale,
+        )?),
+        None => None,
+    };
+    let mut buzzer = Buzzer::default();
+    let mut samples = vec![0.0; buzzer.samples_per_frame()];
+    let mut wav = match &args.wav {
+        Some(path) => Some(WavWriter::new(
+            BufWriter::new(File::create(path)?),
+            buzzer.sample_rate(),
+        )?),
+        None => None,
+    };
+
+    for _ in 0..args.frames {
+        emu.run_frame(args.machine.ticks_per_frame);
+
+        if gif.is_some() || video.is_some() {
+            let display = emu.hires_display();
+            if let Some(gif) = gif.as_mut() {
+                gif.add_frame(&display)?;
+            }
+            if let Some(video) = video.as_mut() {
+                renderer.render(&display, HIRES_WIDTH, HIRES_HEIGHT);
+                video.add_frame(&renderer)?;
+            }
+        }
+        if let Some(wav) = wav.as_mut() {
+            buzzer.fill(emu.is_sounding(), &mut samples);
+            wav.add_samples(&samples)?;
+        }
+    }
+
+    if let Some(fault) = emu.fault() {
+        eprintln!("chip8: warning: {}", fault);
+    }
+
+    if let Some(gif) = gif {
+        gif.finish()?;
+    }
+    if let Some(video) = video {
+        video.finish()?;
+    }
+    if let Some(wav) = wav {
+        wav.finish()?;
+    }
+
+    if let Some(path) = &args.png {
+        screenshot(path, &emu, args.scale, palette)?;
+    }
+    if let Some(path) = &args.svg {
+        let (width, height) = emu.display_size();
+        capture::write_svg(
+            BufWriter::new(File::create(path)?),
+            emu.get_display(),
+            width,
+            height,
+            &palette,
+        )?;
+    }
+
+    Ok(())
+}
+
+// PNG of the current screen, hi-res pixels are half of scale rounded up so the image keeps its size
+pub fn screenshot(
+    path: &Path,
+    emu: &Emu,
+    scale: usize,
+    palette: Palette,
+) -> Result<(), Box<dyn Error>> {
+    let (width, height) = emu.display_size();
+    let mut renderer = Renderer::new(palette);
+    renderer.set_scale(if emu.is_hires() {
+        scale.div_ceil(2).max(1)
+    } else {
+        scale
+    });
+    renderer.render(emu.get_display(), width, height);
+    capture::write_png(BufWriter::new(File::create(path)?), &renderer)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use chip8_core::cfg::{analyze, Cfg};
+use clap::{Args, ValueEnum};
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Dot,
+    Json,
+}
+
+#[derive(Args)]
+pub struct CfgArgs {
+    rom: PathBuf,
+    /// text for a summary, dot for Graphviz or json
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+    /// Write to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: CfgArgs) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&args.rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    let cfg = analyze(&rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    let text = match args.format {
+        Format::Dot => cfg.to_dot(),
+        Format::Json => cfg.to_json(),
+        Format::Text => {
+            let mut out = Vec::new();
+            summary(&mut out, &cfg)?;
+            String::from_utf8(out)?
+        }
+    };
+    match &args.output {
+        Some(path) => fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            out.write_all(text.as_bytes())?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn summary(out: &mut impl Write, cfg: &Cfg) -> io::Result<()> {
+    let code: usize = cfg
+        .blocks
+        .values()
+        .map(|b| (b.last - b.start) as usize + 1)
+        .sum();
+    let data: usize = cfg
+        .data
+        .iter()
+        .map(|&(first, last)| (last - first) as usize + 1)
+        .sum();
+    writeln!(out, "{} bytes of code in {} blocks", code, cfg.blocks.len())?;
+    writeln!(out, "{} bytes of data", data)?;
+    writeln!(out, "Subroutines:")?;
+    for sub in &cfg.subroutines {
+        let plural = if sub.blocks.len() == 1 { "" } else { "s" };
+        writeln!(
+            out,
+            "  {:03X}  {} block{}",
+            sub.entry,
+            sub.blocks.len(),
+            plural
+        )?;
+    }
+    if !cfg.data.is_empty() {
+        writeln!(out, "Data:")?;
+        for &(first, last) in &cfg.data {
+            writeln!(
+                out,
+                "  {:03X}-{:03X}  {} bytes",
+                first,
+                last,
+                (last - first) as usize + 1
+            )?;
+        }
+    }
+    if !cfg.warnings.is_empty() {
+        writeln!(out, "Warnings:")?;
+        for warning in &cfg.warnings {
+            writeln!(out, "  {}", warning)?;
+        }
+    }
+    Ok(())
+}
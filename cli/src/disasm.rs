@@ -0,0 +1,48 @@
+use chip8_core::disasm::disassemble;
+use chip8_core::DEFAULT_START_ADDR;
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct DisasmArgs {
+    rom: PathBuf,
+    /// Only the mnemonics, which `chip8 asm` builds back into the same rom
+    #[arg(long)]
+    plain: bool,
+}
+
+// every two bytes are decoded, so data shows up as instructions too
+pub fn run(args: DisasmArgs) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&args.rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    let start = DEFAULT_START_ADDR as usize;
+    let mut ram = vec![0; start];
+    ram.extend_from_slice(&rom);
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for line in disassemble(&ram, start as u16, rom.len() / 2) {
+        if args.plain {
+            writeln!(out, "{}", line.source())?;
+        } else {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    // an odd length leaves one byte over
+    if let Some(&last) = rom.last().filter(|_| rom.len() % 2 == 1) {
+        if args.plain {
+            writeln!(out, "DB {:#04X}", last)?;
+        } else {
+            writeln!(
+                out,
+                "{:03X}: {:02X}    DB {:#04X}",
+                ram.len() - 1,
+                last,
+                last
+            )?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use crate::machine::{parse_addr, MachineArgs};
+use clap::Args;
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+
+const BYTES_PER_LINE: usize = 16;
+
+#[derive(Args)]
+pub struct HexdumpArgs {
+    #[command(flatten)]
+    machine: MachineArgs,
+    /// Number of 60Hz frames to run first
+    #[arg(long, default_value_t = 0)]
+    frames: usize,
+    /// First address to show, in hex
+    #[arg(long, value_parser = parse_addr, default_value = "0")]
+    start: u16,
+    /// Number of bytes to show, all of memory from --start by default
+    #[arg(long)]
+    len: Option<usize>,
+}
+
+pub fn run(args: HexdumpArgs) -> Result<(), Box<dyn Error>> {
+    let mut emu = args.machine.load()?;
+    for _ in 0..args.frames {
+        emu.run_frame(args.machine.ticks_per_frame);
+    }
+    if let Some(fault) = emu.fault() {
+        eprintln!("chip8: warning: {}", fault);
+    }
+
+    let ram = emu.ram();
+    let start = (args.start as usize).min(ram.len());
+    let end = match args.len {
+        Some(len) => start.saturating_add(len).min(ram.len()),
+        None => ram.len(),
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    for (i, chunk) in ram[start..end].chunks(BYTES_PER_LINE).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let text: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(
+            out,
+            "{:04X}  {:<width$}  |{}|",
+            start + i * BYTES_PER_LINE,
+            hex.join(" "),
+            text,
+            width = BYTES_PER_LINE * 3 - 1
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use chip8_core::disasm::{disassemble, Instruction};
+use chip8_core::romdb::{self, RomDb};
+use chip8_core::{rom_hash, DEFAULT_START_ADDR};
+use clap::Args;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct InfoArgs {
+    rom: PathBuf,
+    /// programs.json from the CHIP-8 community database, for the title and platform
+    #[arg(long)]
+    romdb: Option<PathBuf>,
+}
+
+pub fn run(args: InfoArgs) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&args.rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    let db = match &args.romdb {
+        Some(path) => RomDb::load(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => RomDb::new(),
+    };
+    let hash = rom_hash(&rom);
+    let info = db.get(&hash);
+
+    // decoded straight through, so any data in the rom is counted too
+    let start = DEFAULT_START_ADDR as usize;
+    let mut ram = vec![0; start];
+    ram.extend_from_slice(&rom);
+    let instructions: Vec<Instruction> = disassemble(&ram, start as u16, rom.len() / 2)
+        .into_iter()
+        .map(|line| line.instruction)
+        .collect();
+    let mut used = BTreeMap::new();
+    for instruction in &instructions {
+        *used.entry(instruction.pattern()).or_insert(0) += 1;
+    }
+
+    println!("File:      {}", args.rom.display());
+    if let Some(info) = info {
+        println!("Title:     {}", info.title);
+        if !info.authors.is_empty() {
+            println!("Authors:   {}", info.authors.join(", "));
+        }
+    }
+    println!("Size:      {} bytes", rom.len());
+    println!("SHA-1:     {}", hash);
+    match info.and_then(|info| info.platform()) {
+        Some(id) => println!(
+            "Platform:  {} (from the database)",
+            romdb::platform_name(id)
+        ),
+        None => println!(
+            "Platform:  {} (guessed from the opcodes)",
+            romdb::platform_name(guess_platform(&instructions))
+        ),
+    }
+    if let Some(tickrate) = info.and_then(|info| info.tickrate) {
+        println!("Speed:     {} instructions per frame", tickrate);
+    }
+    println!("Opcodes:");
+    for (pattern, count) in used {
+        println!("  {}  {}", pattern, count);
+    }
+    Ok(())
+}
+
+// only the SUPER-CHIP additions this emulator knows about give a rom away
+fn guess_platform(instructions: &[Instruction]) -> &'static str {
+    let schip = instructions.iter().any(|instruction| {
+        matches!(
+            instruction,
+            Instruction::Low | Instruction::High | Instruction::Drw { n: 0, .. }
+        )
+    });
+    if schip {
+        "superchip"
+    } else {
+        "originalChip8"
+    }
+}
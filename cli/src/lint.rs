@@ -0,0 +1,34 @@
+use chip8_core::lint::lint;
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct LintArgs {
+    rom: PathBuf,
+}
+
+pub fn run(args: LintArgs) -> Result<(), Box<dyn Error>> {
+    let rom = fs::read(&args.rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    let report = lint(&rom).map_err(|e| format!("{}: {}", args.rom.display(), e))?;
+    for finding in &report.findings {
+        println!("{}", finding);
+    }
+    if !report.findings.is_empty() {
+        println!();
+    }
+
+    let q = report.suggested;
+    match q.profile_name() {
+        Some(name) => println!("Suggested quirks: {} (--quirks {})", name, name),
+        None => println!(
+            "Suggested quirks: vf_reset={} memory_increment={} clipping={} shift_vy={} jump_vx={}",
+            q.vf_reset, q.memory_increment, q.clipping, q.shift_vy, q.jump_vx
+        ),
+    }
+    for reason in &report.reasons {
+        println!("  {}", reason);
+    }
+    Ok(())
+}
@@ -0,0 +1,35 @@
+use chip8_core::{Emu, Quirks};
+use clap::Args;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+// what every command that runs a rom needs
+#[derive(Args)]
+pub struct MachineArgs {
+    pub rom: PathBuf,
+    /// Instructions per 60Hz frame
+    #[arg(long, default_value_t = 10)]
+    pub ticks_per_frame: usize,
+    /// One of modern, vip, schip or xochip
+    #[arg(long, default_value = "modern")]
+    pub quirks: String,
+}
+
+impl MachineArgs {
+    pub fn load(&self) -> Result<Emu, Box<dyn Error>> {
+        let quirks = Quirks::profile(&self.quirks)
+            .ok_or_else(|| format!("unknown quirks {}", self.quirks))?;
+        let rom = fs::read(&self.rom).map_err(|e| format!("{}: {}", self.rom.display(), e))?;
+        let mut emu = Emu::new();
+        emu.set_quirks(quirks);
+        emu.load(&rom)?;
+        Ok(emu)
+    }
+}
+
+// addresses are hex with or without 0x, the way listings show them
+pub fn parse_addr(s: &str) -> Result<u16, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{} isn't a hex address", s))
+}
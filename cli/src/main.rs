@@ -0,0 +1,62 @@
+use clap::{Parser, Subcommand};
+use std::process;
+
+mod asm;
+mod capture;
+mod cfg;
+mod disasm;
+mod hexdump;
+mod info;
+mod lint;
+mod machine;
+mod run;
+mod trace;
+
+#[derive(Parser)]
+#[command(name = "chip8", about = "Command line tools for chip8_core")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run a rom without a window and export what it drew and played
+    Capture(capture::CaptureArgs),
+    /// Print a listing of a rom
+    Disasm(disasm::DisasmArgs),
+    /// Build a rom from the mnemonics disasm prints, or from Octo source
+    Asm(asm::AsmArgs),
+    /// Show a rom's size, hash, platform and the opcodes it uses
+    Info(info::InfoArgs),
+    /// Run a rom for a number of frames and show or save the screen
+    Run(run::RunArgs),
+    /// Log every instruction a rom executes along with the registers
+    Trace(trace::TraceArgs),
+    /// Print memory after running a rom for a number of frames
+    Hexdump(hexdump::HexdumpArgs),
+    /// Recover a rom's control flow graph, as a summary, Graphviz or JSON
+    Cfg(cfg::CfgArgs),
+    /// Report what in a rom depends on quirks and suggest a profile
+    Lint(lint::LintArgs),
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Capture(args) => capture::run(args),
+        Command::Disasm(args) => disasm::run(args),
+        Command::Asm(args) => asm::run(args),
+        Command::Info(args) => info::run(args),
+        Command::Run(args) => run::run(args),
+        Command::Trace(args) => trace::run(args),
+        Command::Hexdump(args) => hexdump::run(args),
+        Command::Cfg(args) => cfg::run(args),
+        Command::Lint(args) => lint::run(args),
+    };
+
+    if let Err(e) = result {
+        eprintln!("chip8: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::capture::screenshot;
+use crate::machine::MachineArgs;
+use chip8_core::render::Palette;
+use clap::Args;
+use std::error::Error;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    machine: MachineArgs,
+    /// Number of 60Hz frames to run
+    #[arg(long, default_value_t = 600)]
+    frames: usize,
+    /// Don't print the screen at the end, for scripts that only want --screenshot
+    #[arg(long)]
+    headless: bool,
+    /// PNG of the last frame
+    #[arg(long)]
+    screenshot: Option<PathBuf>,
+    /// Pixels per lo-res pixel in the screenshot, hi-res ones are half this rounded up
+    #[arg(long, default_value_t = 10)]
+    scale: usize,
+}
+
+pub fn run(args: RunArgs) -> Result<(), Box<dyn Error>> {
+    let mut emu = args.machine.load()?;
+    for _ in 0..args.frames {
+        emu.run_frame(args.machine.ticks_per_frame);
+        if emu.fault().is_some() {
+            break;
+        }
+    }
+
+    if let Some(path) = &args.screenshot {
+        screenshot(path, &emu, args.scale, Palette::default())?;
+    }
+    if !args.headless {
+        let (width, _) = emu.display_size();
+        print!("{}", text_screen(emu.get_display(), width));
+    }
+    if let Some(fault) = emu.fault() {
+        return Err(fault.to_string().into());
+    }
+    Ok(())
+}
+
+// two rows of pixels to a line of half blocks, so the screen keeps its shape
+fn text_screen(display: &[bool], width: usize) -> String {
+    let rows: Vec<&[bool]> = display.chunks(width).collect();
+    let mut text = String::new();
+    for pair in rows.chunks(2) {
+        for x in 0..width {
+            let top = pair[0][x];
+            let bottom = pair.get(1).is_some_and(|row| row[x]);
+            text.push(match (top, bottom) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
@@ -0,0 +1,57 @@
+use crate::machine::MachineArgs;
+use chip8_core::disasm::Instruction;
+use chip8_core::Emu;
+use clap::Args;
+use std::error::Error;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Args)]
+pub struct TraceArgs {
+    #[command(flatten)]
+    machine: MachineArgs,
+    /// Number of instructions to run
+    #[arg(long, default_value_t = 1000)]
+    steps: usize,
+}
+
+// one line per instruction with the registers as they were before it ran. The timers tick every
+// --ticks-per-frame instructions, the same as a frame would
+pub fn run(args: TraceArgs) -> Result<(), Box<dyn Error>> {
+    let mut emu = args.machine.load()?;
+    let ticks_per_frame = args.machine.ticks_per_frame.max(1);
+    let mut out = BufWriter::new(io::stdout().lock());
+    for step in 0..args.steps {
+        writeln!(out, "{}", trace_line(&emu))?;
+        emu.tick();
+        if let Some(fault) = emu.fault() {
+            writeln!(out, "{}", fault)?;
+            break;
+        }
+        if (step + 1) % ticks_per_frame == 0 {
+            emu.tick_timers();
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn trace_line(emu: &Emu) -> String {
+    let pc = emu.pc() as usize;
+    let ram = emu.ram();
+    let op = match (ram.get(pc), ram.get(pc + 1)) {
+        (Some(&high), Some(&low)) => u16::from_be_bytes([high, low]),
+        _ => 0,
+    };
+    let v: Vec<String> = emu.v_regs().iter().map(|v| format!("{:02X}", v)).collect();
+    format!(
+        "{:03X}  {:04X}  {:<18}  V={}  I={:03X}  SP={:X}  DT={:02X}  ST={:02X}",
+        pc,
+        op,
+        Instruction::decode(op).to_string(),
+        v.join(" "),
+        emu.i_reg(),
+        emu.sp(),
+        emu.delay_timer(),
+        emu.sound_timer()
+    )
+}
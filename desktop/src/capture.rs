@@ -0,0 +1,143 @@
+use chip8_core::audio::Buzzer;
+use chip8_core::capture::{self, CaptureError, GifRecorder, WavWriter, Y4mWriter};
+use chip8_core::render::{Palette, Renderer};
+use chip8_core::runner::Frame;
+use chip8_core::{hires_pixels, HIRES_HEIGHT, HIRES_WIDTH};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// captures are written at a fixed size rather than whatever the window happens to be, the same
+// for lo-res and hi-res
+const CAPTURE_WIDTH: usize = 640;
+// recordings are made on the hi-res grid so a rom can switch resolution partway through
+const RECORD_SCALE: usize = CAPTURE_WIDTH / HIRES_WIDTH;
+
+type Out = BufWriter<File>;
+
+// screenshots and recordings started from the desktop hotkeys, written to the working directory
+pub struct Capture {
+    renderer: Renderer,
+    buzzer: Buzzer,
+    samples: Vec<f32>,
+    gif: Option<(PathBuf, GifRecorder<Out>)>,
+    video: Option<(PathBuf, Y4mWriter<Out>, WavWriter<Out>)>,
+}
+
+impl Capture {
+    pub fn new(palette: Palette) -> Self {
+        let renderer = Renderer::new(palette);
+        let buzzer = Buzzer::default();
+        let samples = vec![0.0; buzzer.samples_per_frame()];
+
+        Self {
+            renderer,
+            buzzer,
+            samples,
+            gif: None,
+            video: None,
+        }
+    }
+
+    pub fn screenshot<R>(&mut self, frame: &Frame<R>) -> Result<PathBuf, CaptureError> {
+        let path = capture_path("png");
+        self.renderer.set_scale(CAPTURE_WIDTH / frame.width);
+        self.renderer
+            .render(&frame.display, frame.width, frame.height);
+        capture::write_png(BufWriter::new(File::create(&path)?), &self.renderer)?;
+        Ok(path)
+    }
+
+    pub fn screenshot_svg<R>(&mut self, frame: &Frame<R>) -> Result<PathBuf, CaptureError> {
+        let path = capture_path("svg");
+        capture::write_svg(
+            BufWriter::new(File::create(&path)?),
+            &frame.display,
+            frame.width,
+            frame.height,
+            &self.renderer.palette,
+        )?;
+        Ok(path)
+    }
+
+    // returns the path of the recording that was started or finished
+    pub fn toggle_gif(&mut self) -> Result<(bool, PathBuf), CaptureError> {
+        if let Some((path, gif)) = self.gif.take() {
+            gif.finish()?;
+            return Ok((false, path));
+        }
+
+        let path = capture_path("gif");
+        let gif = GifRecorder::new(
+            BufWriter::new(File::create(&path)?),
+            HIRES_WIDTH,
+            HIRES_HEIGHT,
+            RECORD_SCALE,
+            &self.renderer.palette,
+        )?;
+        self.gif = Some((path.clone(), gif));
+        Ok((true, path))
+    }
+
+    // the video and the buzzer go to a .y4m and a .wav with the same name
+    pub fn toggle_video(&mut self) -> Result<(bool, PathBuf), CaptureError> {
+        if let Some((path, video, wav)) = self.video.take() {
+            video.finish()?;
+            wav.finish()?;
+            return Ok((false, path));
+        }
+
+        let path = capture_path("y4m");
+        let video = Y4mWriter::new(
+            BufWriter::new(File::create(&path)?),
+            HIRES_WIDTH * RECORD_SCALE,
+            HIRES_HEIGHT * RECORD_SCALE,
+        )?;
+        let wav = WavWriter::new(
+            BufWriter::new(File::create(path.with_extension("wav"))?),
+            self.buzzer.sample_rate(),
+        )?;
+        self.video = Some((path.clone(), video, wav));
+        Ok((true, path))
+    }
+
+    // called once per 60Hz frame
+    pub fn frame<R>(&mut self, frame: &Frame<R>) -> Result<(), CaptureError> {
+        if self.gif.is_none() && self.video.is_none() {
+            return Ok(());
+        }
+        let display = hires_pixels(&frame.display);
+        if let Some((_, gif)) = self.gif.as_mut() {
+            gif.add_frame(&display)?;
+        }
+        if let Some((_, video, wav)) = self.video.as_mut() {
+            self.renderer.set_scale(RECORD_SCALE);
+            self.renderer.render(&display, HIRES_WIDTH, HIRES_HEIGHT);
+            video.add_frame(&self.renderer)?;
+            self.buzzer.fill(frame.sounding, &mut self.samples);
+            wav.add_samples(&self.samples)?;
+        }
+
+        Ok(())
+    }
+
+    // finishes anything still recording, otherwise the files are left without their trailers
+    pub fn stop(&mut self) -> Result<(), CaptureError> {
+        if self.gif.is_some() {
+            self.toggle_gif()?;
+        }
+        if self.video.is_some() {
+            self.toggle_video()?;
+        }
+        Ok(())
+    }
+}
+
+fn capture_path(extension: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    PathBuf::from(format!("chip8-{}.{}", millis, extension))
+}
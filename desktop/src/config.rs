@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SCALE: u32 = 15;
+pub const DEFAULT_TICKS_PER_FRAME: usize = 10;
+
+// settings that can come from the command line, the config file or a rom's overrides. Anything
+// left as None falls through to the next place
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Options {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ticks_per_frame: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub palette: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mute: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integer_scale: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font: Option<PathBuf>,
+}
+
+impl Options {
+    // anything set in other wins
+    pub fn merge(&mut self, other: &Options) {
+        fn take<T: Clone>(into: &mut Option<T>, from: &Option<T>) {
+            if from.is_some() {
+                into.clone_from(from);
+            }
+        }
+        take(&mut self.scale, &other.scale);
+        take(&mut self.ticks_per_frame, &other.ticks_per_frame);
+        take(&mut self.quirks, &other.quirks);
+        take(&mut self.palette, &other.palette);
+        take(&mut self.keymap, &other.keymap);
+        take(&mut self.mute, &other.mute);
+        take(&mut self.fullscreen, &other.fullscreen);
+        take(&mut self.integer_scale, &other.integer_scale);
+        take(&mut self.font, &other.font);
+    }
+}
+
+// the config file. Top level keys are the defaults and [roms.<sha1>] tables override them for
+// one rom, e.g.
+//
+//     scale = 12
+//     palette = "amber"
+//     rom-dirs = ["/home/me/roms"]
+//
+//     [roms.0123456789abcdef0123456789abcdef01234567]
+//     ticks-per-frame = 30
+//     quirks = "schip"
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(flatten)]
+    pub defaults: Options,
+    // where the rom picker looks for roms
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rom_dirs: Vec<PathBuf>,
+    // programs.json from the CHIP-8 community database, for titles, platforms and speeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub romdb: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub roms: BTreeMap<String, Options>,
+}
+
+impl Config {
+    // a missing file is just an empty config
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    // defaults, then the rom's overrides, then whatever was given on the command line
+    pub fn options_for(&self, rom_hash: &str, cli: &Options) -> Options {
+        let mut options = self.defaults.clone();
+        if let Some(rom) = self.roms.get(rom_hash) {
+            options.merge(rom);
+        }
+        options.merge(cli);
+        options
+    }
+}
+
+// $XDG_CONFIG_HOME/chip8/desktop.toml or the platform's equivalent
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("chip8").join("desktop.toml"))
+}
@@ -0,0 +1,61 @@
+use sdl2::keyboard::{Keycode, Mod};
+
+// the function keys and backquote are kept for controlling the emulator, everything else goes to
+// the keymap
+//
+//     F1 pause            F5 quick save        F9 fast forward
+//     F2 reset            F6 quick load        F10 speed up, shift for down
+//     F3 step             F7 previous slot     F11 gif, shift for video
+//     F4 advance frame    F8 next slot         F12 png, shift for svg
+//     ` debug overlay, shift for the memory window
+//     alt+enter fullscreen
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hotkey {
+    Pause,
+    Reset,
+    Step,
+    AdvanceFrame,
+    QuickSave,
+    QuickLoad,
+    PrevSlot,
+    NextSlot,
+    FastForward,
+    SpeedUp,
+    SpeedDown,
+    ToggleGif,
+    ToggleVideo,
+    Screenshot,
+    ScreenshotSvg,
+    Overlay,
+    MemoryWindow,
+    Fullscreen,
+}
+
+pub fn hotkey(key: Keycode, keymod: Mod) -> Option<Hotkey> {
+    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+    let alt = keymod.intersects(Mod::LALTMOD | Mod::RALTMOD);
+    if alt && matches!(key, Keycode::Return | Keycode::KpEnter) {
+        return Some(Hotkey::Fullscreen);
+    }
+    let hotkey = match (key, shift) {
+        (Keycode::F1, _) => Hotkey::Pause,
+        (Keycode::F2, _) => Hotkey::Reset,
+        (Keycode::F3, _) => Hotkey::Step,
+        (Keycode::F4, _) => Hotkey::AdvanceFrame,
+        (Keycode::F5, _) => Hotkey::QuickSave,
+        (Keycode::F6, _) => Hotkey::QuickLoad,
+        (Keycode::F7, _) => Hotkey::PrevSlot,
+        (Keycode::F8, _) => Hotkey::NextSlot,
+        (Keycode::F9, _) => Hotkey::FastForward,
+        (Keycode::F10, false) => Hotkey::SpeedUp,
+        (Keycode::F10, true) => Hotkey::SpeedDown,
+        (Keycode::F11, false) => Hotkey::ToggleGif,
+        (Keycode::F11, true) => Hotkey::ToggleVideo,
+        (Keycode::F12, false) => Hotkey::Screenshot,
+        (Keycode::F12, true) => Hotkey::ScreenshotSvg,
+        (Keycode::Backquote, false) => Hotkey::Overlay,
+        (Keycode::Backquote, true) => Hotkey::MemoryWindow,
+        _ => return None,
+    };
+    Some(hotkey)
+}
@@ -0,0 +1,122 @@
+use chip8_core::rom_hash;
+use chip8_core::romdb::{self, RomDb};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const EXTENSIONS: [&str; 4] = ["ch8", "c8", "sc8", "xo8"];
+// how far below a rom directory to look
+const MAX_DEPTH: usize = 4;
+const MAX_RECENT: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub title: String,
+    pub platform: Option<String>,
+    pub recent: bool,
+}
+
+impl Entry {
+    fn new(path: PathBuf, db: &RomDb, recent: bool) -> Self {
+        let info = fs::read(&path)
+            .ok()
+            .and_then(|rom| db.get(&rom_hash(&rom)).cloned());
+        let title = match &info {
+            Some(info) => info.title.clone(),
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        let platform = info
+            .as_ref()
+            .and_then(|info| info.platform())
+            .map(|id| romdb::platform_name(id).to_string());
+        Self {
+            path,
+            title,
+            platform,
+            recent,
+        }
+    }
+}
+
+// recently played roms first, then everything in the rom directories sorted by title
+pub fn entries(dirs: &[PathBuf], db: &RomDb) -> Vec<Entry> {
+    let recent = load_recent();
+    let mut entries: Vec<Entry> = recent
+        .iter()
+        .filter(|path| path.is_file())
+        .map(|path| Entry::new(path.clone(), db, true))
+        .collect();
+
+    let mut found = Vec::new();
+    for dir in dirs {
+        scan(dir, 0, &mut found);
+    }
+    let mut found: Vec<Entry> = found
+        .into_iter()
+        .filter(|path| !recent.contains(path))
+        .map(|path| Entry::new(path, db, false))
+        .collect();
+    found.sort_by_cached_key(|entry| entry.title.to_lowercase());
+    entries.extend(found);
+    entries
+}
+
+pub fn is_rom(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// unreadable directories are skipped rather than stopping the scan
+fn scan(dir: &Path, depth: usize, found: &mut Vec<PathBuf>) {
+    let Ok(dir) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in dir.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                scan(&path, depth + 1, found);
+            }
+        } else if is_rom(&path) {
+            found.push(fs::canonicalize(&path).unwrap_or(path));
+        }
+    }
+}
+
+// one path per line, most recent first
+fn recent_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("chip8").join("recent.txt"))
+}
+
+pub fn load_recent() -> Vec<PathBuf> {
+    recent_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().map(PathBuf::from).collect())
+        .unwrap_or_default()
+}
+
+pub fn remember(rom: &Path) -> io::Result<()> {
+    let Some(path) = recent_path() else {
+        return Ok(());
+    };
+    let rom = fs::canonicalize(rom)?;
+    let mut recent = load_recent();
+    recent.retain(|p| *p != rom);
+    recent.insert(0, rom);
+    recent.truncate(MAX_RECENT);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let contents: Vec<String> = recent
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    fs::write(path, contents.join("\n") + "\n")
+}
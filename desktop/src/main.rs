@@ -1,28 +1,733 @@
+use capture::Capture;
+use chip8_core::keymap::Keymap;
+use chip8_core::render::{Palette, Renderer};
+use chip8_core::romdb::{RomDb, RomInfo};
+use chip8_core::runner::{Command, Frame, Runner, RunnerError, FRAME_TIME};
 use chip8_core::*;
-use sdl2::event::Event;
-use std::env;
+use clap::Parser;
+use config::{Config, Options, DEFAULT_SCALE, DEFAULT_TICKS_PER_FRAME};
+use hotkeys::Hotkey;
+use osd::{Notifier, Text};
+use overlay::Overlay;
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Scancode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::video::{FullscreenType, Window, WindowContext};
+use sdl2::VideoSubsystem;
+use session::{Reload, Session, Snapshot};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use watch::RomWatcher;
 
-const SCALE: u32 = 15;
-const WINDOW_WIDTH: u32 = (SCREEN_WIDTH as u32) * SCALE;
-const WINDOW_HEIGHT: u32 = (SCREEN_HEIGHT as u32) * SCALE;
+mod capture;
+mod config;
+mod hotkeys;
+mod library;
+mod osd;
+mod overlay;
+mod picker;
+mod session;
+mod viewport;
+mod watch;
+
+#[derive(Parser)]
+#[command(name = "chip8", about = "Play a chip-8 rom")]
+struct Cli {
+    /// Rom to play, leave it out to pick one from the library
+    rom: Option<PathBuf>,
+    /// Window pixels per lo-res chip-8 pixel, the window can be resized after [default: 15]
+    #[arg(long)]
+    scale: Option<u32>,
+    /// Instructions run each 60Hz frame [default: 10]
+    #[arg(long)]
+    ticks_per_frame: Option<usize>,
+    /// Quirk profile, one of modern, vip, schip or xochip [default: modern]
+    #[arg(long)]
+    quirks: Option<String>,
+    /// One of classic, amber, green or high-contrast [default: classic]
+    #[arg(long)]
+    palette: Option<String>,
+    /// One of qwerty, azerty or numpad, or a .toml or .json keymap file [default: qwerty]
+    #[arg(long)]
+    keymap: Option<String>,
+    #[arg(long)]
+    mute: bool,
+    /// Alt+Enter toggles it while playing
+    #[arg(long)]
+    fullscreen: bool,
+    /// Only scale the game by whole numbers, leaving a border if the window doesn't fit exactly
+    #[arg(long)]
+    integer_scale: bool,
+    /// TrueType font for on-screen messages, found automatically if not given
+    #[arg(long, value_name = "FILE")]
+    font: Option<PathBuf>,
+    /// Start paused, F1 resumes and F3 steps
+    #[arg(long)]
+    paused: bool,
+    /// Save state to load before starting
+    #[arg(long, value_name = "FILE")]
+    state: Option<PathBuf>,
+    /// Reload the rom whenever its file changes
+    #[arg(long)]
+    watch: bool,
+    /// Keep the keys that are held down when the rom reloads
+    #[arg(long, requires = "watch")]
+    keep_keys: bool,
+    /// Restore this save state after each reload, with the new rom written over its memory
+    #[arg(long, value_name = "FILE", requires = "watch")]
+    reload_state: Option<PathBuf>,
+    /// Run to this address after each reload and pause there, in hex
+    #[arg(long, value_name = "ADDR", value_parser = parse_addr, requires = "watch")]
+    run_to: Option<u16>,
+    /// Directory the rom picker looks in, can be given more than once
+    #[arg(long, value_name = "DIR")]
+    rom_dir: Vec<PathBuf>,
+    /// programs.json from the CHIP-8 community database, for rom titles, platforms and speeds
+    #[arg(long, value_name = "FILE")]
+    romdb: Option<PathBuf>,
+    /// Config file to use instead of the one in the user's config directory
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Store the options given here in the config file as the defaults, along with the rom
+    /// directories and database
+    #[arg(long, conflicts_with = "save_rom_config")]
+    save_config: bool,
+    /// Store the options given here in the config file for this rom only
+    #[arg(long)]
+    save_rom_config: bool,
+}
+
+impl Cli {
+    // the options that were actually given, so the config file fills in the rest
+    fn options(&self) -> Options {
+        Options {
+            scale: self.scale,
+            ticks_per_frame: self.ticks_per_frame,
+            quirks: self.quirks.clone(),
+            palette: self.palette.clone(),
+            keymap: self.keymap.clone(),
+            mute: self.mute.then_some(true),
+            fullscreen: self.fullscreen.then_some(true),
+            integer_scale: self.integer_scale.then_some(true),
+            font: self.font.clone(),
+        }
+    }
+}
+
+// everything the game loop needs, resolved from the options and what the rom database knows
+struct Settings {
+    scale: u32,
+    ticks_per_frame: usize,
+    quirks: Quirks,
+    palette: Palette,
+    keymap: Keymap,
+    mute: bool,
+    fullscreen: bool,
+    integer_scale: bool,
+    font: Option<PathBuf>,
+}
+
+impl Settings {
+    fn resolve(options: &Options, info: Option<&RomInfo>) -> Result<Self, Box<dyn Error>> {
+        let quirks = match &options.quirks {
+            None => info.and_then(RomInfo::quirks).unwrap_or_default(),
+            Some(name) => {
+                Quirks::profile(name).ok_or_else(|| format!("unknown quirks {}", name))?
+            }
+        };
+        let palette = match &options.palette {
+            None => Palette::default(),
+            Some(name) => {
+                Palette::preset(name).ok_or_else(|| format!("unknown palette {}", name))?
+            }
+        };
+        let keymap = match &options.keymap {
+            None => Keymap::qwerty(),
+            Some(name) => match Keymap::preset(name) {
+                Some(keymap) => keymap,
+                None => {
+                    Keymap::load(Path::new(name)).map_err(|e| format!("keymap {}: {}", name, e))?
+                }
+            },
+        };
+        let scale = options.scale.unwrap_or(DEFAULT_SCALE);
+        if scale == 0 {
+            return Err("scale has to be at least 1".into());
+        }
+
+        Ok(Self {
+            scale,
+            ticks_per_frame: options
+                .ticks_per_frame
+                .or(info.and_then(|info| info.tickrate))
+                .unwrap_or(DEFAULT_TICKS_PER_FRAME),
+            quirks,
+            palette,
+            keymap,
+            mute: options.mute.unwrap_or(false),
+            fullscreen: options.fullscreen.unwrap_or(false),
+            integer_scale: options.integer_scale.unwrap_or(false),
+            font: osd::find_font(options.font.as_deref()),
+        })
+    }
+}
+
+fn parse_addr(s: &str) -> Result<u16, String> {
+    let digits = s.strip_prefix("0x").unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("{} isn't a hex address", s))
+}
 
 fn main() {
-    let args: Vec<_> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: give path to rom");
-        return;
+    let cli = Cli::parse();
+    if let Err(e) = run(cli) {
+        eprintln!("chip8: {}", e);
+        process::exit(1);
+    }
+}
+
+// the settings a rom is played with. Its session goes off to the emulation thread
+struct Game {
+    settings: Settings,
+    title: String,
+}
+
+impl Game {
+    fn load(
+        path: &Path,
+        config: &Config,
+        cli: &Cli,
+        db: &RomDb,
+    ) -> Result<(Self, Session), Box<dyn Error>> {
+        let rom = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let hash = rom_hash(&rom);
+        let info = db.get(&hash);
+        let settings = Settings::resolve(&config.options_for(&hash, &cli.options()), info)?;
+
+        let mut chip8 = Emu::new();
+        chip8.set_quirks(settings.quirks);
+        chip8.load(&rom)?;
+        let title = match info {
+            Some(info) => info.title.clone(),
+            None => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+        if let Err(e) = library::remember(path) {
+            eprintln!("Unable to remember {}: {}", path.display(), e);
+        }
+
+        let session = Session::new(chip8, rom, settings.ticks_per_frame);
+        Ok((Self { settings, title }, session))
+    }
+
+    // sizes the window for this game's scale, unless it's fullscreen
+    fn apply(&self, window: &mut Window) -> Result<(), Box<dyn Error>> {
+        window.set_title(&format!("Chip-8 Emulator - {}", self.title))?;
+        if self.settings.fullscreen {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        } else {
+            window.set_fullscreen(FullscreenType::Off)?;
+            window.set_size(
+                SCREEN_WIDTH as u32 * self.settings.scale,
+                SCREEN_HEIGHT as u32 * self.settings.scale,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config_path = cli
+        .config
+        .clone()
+        .or_else(config::default_path)
+        .ok_or("no config directory, pass --config")?;
+    let mut config =
+        Config::load(&config_path).map_err(|e| format!("{}: {}", config_path.display(), e))?;
+    config.rom_dirs.extend(cli.rom_dir.iter().cloned());
+    if cli.romdb.is_some() {
+        config.romdb.clone_from(&cli.romdb);
+    }
+
+    if cli.save_config || cli.save_rom_config {
+        // the file is reloaded so the directories above only get saved when asked for
+        let mut saved_config = Config::load(&config_path)?;
+        if cli.save_config {
+            saved_config.defaults.merge(&cli.options());
+            saved_config.rom_dirs.clone_from(&config.rom_dirs);
+            saved_config.romdb.clone_from(&config.romdb);
+        } else {
+            let rom = cli.rom.as_ref().ok_or("--save-rom-config needs a rom")?;
+            let hash = rom_hash(&fs::read(rom).map_err(|e| format!("{}: {}", rom.display(), e))?);
+            saved_config
+                .roms
+                .entry(hash)
+                .or_default()
+                .merge(&cli.options());
+        }
+        saved_config.save(&config_path)?;
+        config.defaults = saved_config.defaults;
+        config.roms = saved_config.roms;
+        println!("Saved options to {}", config_path.display());
     }
+
+    let db = match &config.romdb {
+        Some(path) => RomDb::load(path).unwrap_or_else(|e| {
+            eprintln!("Unable to load rom database {}: {}", path.display(), e);
+            RomDb::new()
+        }),
+        None => RomDb::new(),
+    };
+    // the window and font are set up before there's a rom, so they go by the defaults
+    let defaults = Settings::resolve(&config.options_for("", &cli.options()), None)?;
+
     // Setup SDL
-    let sdl_context = sdl2::init().unwrap();
-    let video_subsystem = sdl_context.video().unwrap();
-    let window = video_subsystem
-        .window("Chip-8 Emulator", WINDOW_WIDTH, WINDOW_HEIGHT)
-        .position_centered()
-        .opengl()
-        .build()
-        .unwrap();
-
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let sdl_context = sdl2::init()?;
+    let video_subsystem = sdl_context.video()?;
+    let mut window = video_subsystem.window(
+        "Chip-8 Emulator",
+        SCREEN_WIDTH as u32 * defaults.scale,
+        SCREEN_HEIGHT as u32 * defaults.scale,
+    );
+    window.position_centered().resizable().opengl();
+    if defaults.fullscreen {
+        window.fullscreen_desktop();
+    }
+    let mut window = window.build()?;
+    window.set_minimum_size(SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)?;
+
+    let mut canvas = window.into_canvas().present_vsync().build()?;
     canvas.clear();
     canvas.present();
+
+    let texture_creator = canvas.texture_creator();
+    let ttf = sdl2::ttf::init()?;
+    let text = match &defaults.font {
+        Some(path) => match Text::load(&ttf, path) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                eprintln!("Unable to load font {}: {}", path.display(), e);
+                None
+            }
+        },
+        None => None,
+    };
+    let mut event_pump = sdl_context.event_pump()?;
+
+    let rom_path = match &cli.rom {
+        Some(path) => path.clone(),
+        None => {
+            let entries = library::entries(&config.rom_dirs, &db);
+            let picked = picker::pick(
+                &mut event_pump,
+                &mut canvas,
+                &texture_creator,
+                text.as_ref(),
+                &entries,
+            )?;
+            match picked {
+                Some(path) => path,
+                None => return Ok(()),
+            }
+        }
+    };
+    let (mut game, mut session) = Game::load(&rom_path, &config, &cli, &db)?;
+    game.apply(canvas.window_mut())?;
+    if let Some(path) = &cli.state {
+        let state = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        session.chip8.load_state(&SaveState::from_bytes(&state)?)?;
+        session.debugger.sync(&session.chip8);
+    }
+    session.paused = cli.paused;
+
+    let reload = Reload {
+        keep_keys: cli.keep_keys,
+        state: match &cli.reload_state {
+            Some(path) => {
+                let state = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                Some(SaveState::from_bytes(&state)?)
+            }
+            None => None,
+        },
+        run_to: cli.run_to,
+    };
+    let mut watcher = cli.watch.then(|| RomWatcher::new(&rom_path));
+
+    // the renderer works at the display's size and SDL stretches the texture over the game's
+    // part of the window. It's made again whenever the rom changes resolution
+    let mut texture = texture_creator.create_texture_streaming(
+        PixelFormatEnum::RGBA32,
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+    )?;
+    let mut renderer = Renderer::new(game.settings.palette);
+    let mut capture = Capture::new(renderer.palette);
+    let mut notifier = Notifier::new(text.is_some());
+    let mut overlay = Overlay::new(text.is_some());
+
+    // no sound card isn't a reason not to play
+    let sound = match open_audio(&sdl_context) {
+        Ok(sound) => Some(sound),
+        Err(e) => {
+            eprintln!("No sound: {}", e);
+            None
+        }
+    };
+    let sample_rate = match &sound {
+        Some(sound) => sound.spec().freq as u32,
+        None => audio::DEFAULT_SAMPLE_RATE,
+    };
+
+    // the game runs on its own thread from here, this one only handles the window. The latest
+    // frame is what gets drawn, captured and shown in the debug views
+    let runner = Runner::spawn(session, sample_rate)?;
+    let mut latest: Option<Frame<Snapshot>> = None;
+
+    let main_window = canvas.window().id();
+
+    'gameloop: loop {
+        for evt in event_pump.poll_iter() {
+            match evt {
+                Event::Quit { .. } => break 'gameloop,
+                // with the memory window open closing either window doesn't quit by itself
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } => {
+                    if window_id == main_window {
+                        break 'gameloop;
+                    }
+                    overlay.close_window();
+                }
+                // dropping a rom on the window switches to it
+                Event::DropFile { filename, .. } => {
+                    let path = PathBuf::from(filename);
+                    match Game::load(&path, &config, &cli, &db) {
+                        Ok((next, session)) => {
+                            if let Err(e) = capture.stop() {
+                                eprintln!("Capture failed: {}", e);
+                            }
+                            runner.run(move |current| *current = session)?;
+                            game = next;
+                            if cli.watch {
+                                watcher = Some(RomWatcher::new(&path));
+                            }
+                            if let Err(e) = game.apply(canvas.window_mut()) {
+                                eprintln!("{}", e);
+                            }
+                            renderer.palette = game.settings.palette;
+                            capture = Capture::new(renderer.palette);
+                            notifier.show(format!("Playing {}", game.title));
+                        }
+                        Err(e) => notifier.show(e.to_string()),
+                    }
+                }
+                Event::MouseButtonDown {
+                    window_id,
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } if Some(window_id) == overlay.window_id() => {
+                    if let Some(addr) = overlay.breakpoint_at(x, y) {
+                        notifier.show(runner.call(move |session| session.toggle_breakpoint(addr))?);
+                    }
+                }
+                Event::KeyDown {
+                    keycode,
+                    scancode,
+                    keymod,
+                    repeat,
+                    ..
+                } => match keycode.and_then(|key| hotkeys::hotkey(key, keymod)) {
+                    Some(hotkey) => {
+                        if !repeat {
+                            let message = handle_hotkey(
+                                hotkey,
+                                canvas.window_mut(),
+                                &runner,
+                                latest.as_ref(),
+                                &mut capture,
+                                &mut overlay,
+                                &video_subsystem,
+                            )?;
+                            notifier.show(message);
+                        }
+                    }
+                    // scancodes are the physical key, so the keymap works whatever the layout
+                    None => {
+                        if let Some(k) = scancode
+                            .and_then(key_code)
+                            .and_then(|code| game.settings.keymap.key(&code))
+                        {
+                            runner.send(Command::Key {
+                                key: k,
+                                pressed: true,
+                            })?;
+                        }
+                    }
+                },
+                Event::KeyUp {
+                    scancode: Some(sc), ..
+                } => {
+                    if let Some(k) = key_code(sc).and_then(|code| game.settings.keymap.key(&code)) {
+                        runner.send(Command::Key {
+                            key: k,
+                            pressed: false,
+                        })?;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(watcher) = &mut watcher {
+            if watcher.poll() {
+                let message = match fs::read(watcher.path()) {
+                    Ok(rom) => {
+                        let reload = reload.clone();
+                        runner.call(move |session| session.reload(rom, &reload))?
+                    }
+                    Err(e) => format!("Reload failed: {}", e),
+                };
+                notifier.show(message);
+            }
+        }
+
+        // waits for the next frame, so this loop runs at the game's pace whether or not there's
+        // vsync. A stall here only delays the picture, the game carries on regardless
+        for frame in runner.recv_frames(FRAME_TIME * 2)? {
+            if let Some(message) = &frame.report.message {
+                notifier.show(message.clone());
+            }
+            for e in &frame.errors {
+                notifier.show(e.to_string());
+            }
+            if !frame.report.paused {
+                if let Err(e) = capture.frame(&frame) {
+                    notifier.show(format!("Recording stopped: {}", e));
+                    capture.stop().ok();
+                }
+            }
+            if let Some(sound) = &sound {
+                if !game.settings.mute {
+                    queue_samples(sound, &frame.samples);
+                }
+            }
+            latest = Some(frame);
+        }
+        let Some(frame) = &latest else {
+            continue;
+        };
+
+        let drawn = draw_screen(
+            frame,
+            &mut renderer,
+            &texture_creator,
+            &mut texture,
+            &mut canvas,
+            game.settings.integer_scale,
+        );
+        if let Err(e) = drawn {
+            eprintln!("{}", e);
+        }
+        if let Some(text) = &text {
+            let drawn = overlay
+                .draw(&mut canvas, &texture_creator, text, &frame.report)
+                .and_then(|()| notifier.draw(&mut canvas, &texture_creator, text))
+                .and_then(|()| overlay.draw_window(text, &frame.report));
+            if let Err(e) = drawn {
+                eprintln!("{}", e);
+            }
+        }
+        canvas.present();
+    }
+
+    if let Err(e) = capture.stop() {
+        eprintln!("Capture failed: {}", e);
+    }
+    Ok(())
+}
+
+// frames can come faster than 60 a second, so only top the queue up to a few frames rather
+// than letting the sound fall further and further behind
+fn queue_samples(queue: &AudioQueue<f32>, samples: &[f32]) {
+    let queued = queue.size() as usize / std::mem::size_of::<f32>();
+    if queued < samples.len() * 3 {
+        queue.queue(samples);
+    }
+}
+
+fn open_audio(sdl_context: &sdl2::Sdl) -> Result<AudioQueue<f32>, String> {
+    let desired = AudioSpecDesired {
+        freq: Some(audio::DEFAULT_SAMPLE_RATE as i32),
+        channels: Some(1),
+        samples: None,
+    };
+    let queue = sdl_context.audio()?.open_queue::<f32, _>(None, &desired)?;
+    queue.resume();
+    Ok(queue)
+}
+
+// only the window, the debug views and captures are handled here, everything else is a call to
+// the session on the emulation thread
+fn handle_hotkey(
+    hotkey: Hotkey,
+    window: &mut Window,
+    runner: &Runner<Session>,
+    frame: Option<&Frame<Snapshot>>,
+    capture: &mut Capture,
+    overlay: &mut Overlay,
+    video: &VideoSubsystem,
+) -> Result<String, RunnerError> {
+    let capture_result = match hotkey {
+        Hotkey::Pause => return runner.call(Session::toggle_pause),
+        Hotkey::Reset => return runner.call(Session::reset),
+        Hotkey::Step => return runner.call(Session::step),
+        Hotkey::AdvanceFrame => return runner.call(Session::advance_frame),
+        Hotkey::QuickSave => return runner.call(Session::quick_save),
+        Hotkey::QuickLoad => return runner.call(Session::quick_load),
+        Hotkey::PrevSlot => return runner.call(Session::prev_slot),
+        Hotkey::NextSlot => return runner.call(Session::next_slot),
+        Hotkey::FastForward => return runner.call(Session::toggle_fast_forward),
+        Hotkey::SpeedUp => return runner.call(Session::speed_up),
+        Hotkey::SpeedDown => return runner.call(Session::speed_down),
+        Hotkey::Overlay => return Ok(overlay.toggle()),
+        Hotkey::MemoryWindow => return Ok(overlay.toggle_window(video)),
+        Hotkey::Fullscreen => return Ok(toggle_fullscreen(window)),
+        Hotkey::Screenshot | Hotkey::ScreenshotSvg => {
+            let Some(frame) = frame else {
+                return Ok("Nothing to capture yet".to_string());
+            };
+            let path = if hotkey == Hotkey::Screenshot {
+                capture.screenshot(frame)
+            } else {
+                capture.screenshot_svg(frame)
+            };
+            path.map(|p| (false, p))
+        }
+        Hotkey::ToggleGif => capture.toggle_gif(),
+        Hotkey::ToggleVideo => capture.toggle_video(),
+    };
+    Ok(match capture_result {
+        Ok((true, path)) => format!("Recording {}", path.display()),
+        Ok((false, path)) => format!("Saved {}", path.display()),
+        Err(e) => format!("Capture failed: {}", e),
+    })
+}
+
+// the window keeps its size, only the game's part of it changes
+fn toggle_fullscreen(window: &mut Window) -> String {
+    let (next, message) = match window.fullscreen_state() {
+        FullscreenType::Off => (FullscreenType::Desktop, "Fullscreen"),
+        _ => (FullscreenType::Off, "Windowed"),
+    };
+    match window.set_fullscreen(next) {
+        Ok(()) => message.to_string(),
+        Err(e) => format!("Unable to change fullscreen: {}", e),
+    }
+}
+
+fn draw_screen<'a>(
+    frame: &Frame<Snapshot>,
+    renderer: &mut Renderer,
+    creator: &'a TextureCreator<WindowContext>,
+    texture: &mut Texture<'a>,
+    canvas: &mut Canvas<Window>,
+    integer_scale: bool,
+) -> Result<(), String> {
+    let (width, height) = (frame.width, frame.height);
+    let query = texture.query();
+    if (query.width, query.height) != (width as u32, height as u32) {
+        *texture = creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, width as u32, height as u32)
+            .map_err(|e| e.to_string())?;
+    }
+    renderer.render(&frame.display, width, height);
+    texture
+        .update(None, renderer.buffer(), renderer.width() * 4)
+        .map_err(|e| e.to_string())?;
+
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    let rect = viewport::game_rect(canvas.output_size()?, integer_scale);
+    canvas.copy(texture, None, rect)
+}
+
+// the KeyboardEvent.code name the keymap uses for a scancode
+fn key_code(sc: Scancode) -> Option<String> {
+    let code = match sc {
+        Scancode::A
+        | Scancode::B
+        | Scancode::C
+        | Scancode::D
+        | Scancode::E
+        | Scancode::F
+        | Scancode::G
+        | Scancode::H
+        | Scancode::I
+        | Scancode::J
+        | Scancode::K
+        | Scancode::L
+        | Scancode::M
+        | Scancode::N
+        | Scancode::O
+        | Scancode::P
+        | Scancode::Q
+        | Scancode::R
+        | Scancode::S
+        | Scancode::T
+        | Scancode::U
+        | Scancode::V
+        | Scancode::W
+        | Scancode::X
+        | Scancode::Y
+        | Scancode::Z => return Some(format!("Key{}", sc.name())),
+        Scancode::Num0
+        | Scancode::Num1
+        | Scancode::Num2
+        | Scancode::Num3
+        | Scancode::Num4
+        | Scancode::Num5
+        | Scancode::Num6
+        | Scancode::Num7
+        | Scancode::Num8
+        | Scancode::Num9 => return Some(format!("Digit{}", sc.name())),
+        Scancode::Kp0 => "Numpad0",
+        Scancode::Kp1 => "Numpad1",
+        Scancode::Kp2 => "Numpad2",
+        Scancode::Kp3 => "Numpad3",
+        Scancode::Kp4 => "Numpad4",
+        Scancode::Kp5 => "Numpad5",
+        Scancode::Kp6 => "Numpad6",
+        Scancode::Kp7 => "Numpad7",
+        Scancode::Kp8 => "Numpad8",
+        Scancode::Kp9 => "Numpad9",
+        Scancode::KpDivide => "NumpadDivide",
+        Scancode::KpMultiply => "NumpadMultiply",
+        Scancode::KpMinus => "NumpadSubtract",
+        Scancode::KpPlus => "NumpadAdd",
+        Scancode::KpEnter => "NumpadEnter",
+        Scancode::KpPeriod => "NumpadDecimal",
+        Scancode::Up => "ArrowUp",
+        Scancode::Down => "ArrowDown",
+        Scancode::Left => "ArrowLeft",
+        Scancode::Right => "ArrowRight",
+        Scancode::Space => "Space",
+        Scancode::Return => "Enter",
+        Scancode::Tab => "Tab",
+        Scancode::LShift => "ShiftLeft",
+        Scancode::RShift => "ShiftRight",
+        Scancode::LCtrl => "ControlLeft",
+        Scancode::RCtrl => "ControlRight",
+        _ => return None,
+    };
+    Some(code.to_string())
 }
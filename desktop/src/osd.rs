@@ -0,0 +1,141 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, TextureCreator};
+use sdl2::ttf::{Font, Sdl2TtfContext};
+use sdl2::video::{Window, WindowContext};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const FONT_SIZE: u16 = 16;
+pub const MARGIN: i32 = 8;
+const SHOW_FOR: Duration = Duration::from_secs(2);
+
+// monospaced fonts that usually come with the OS, tried in order when none is configured
+const FONT_PATHS: [&str; 7] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/TTF/DejaVuSansMono.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationMono-Regular.ttf",
+    "/System/Library/Fonts/Menlo.ttc",
+    "/Library/Fonts/Courier New.ttf",
+    "C:\\Windows\\Fonts\\consola.ttf",
+];
+
+pub fn find_font(configured: Option<&Path>) -> Option<PathBuf> {
+    match configured {
+        Some(path) => Some(path.to_path_buf()),
+        None => FONT_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.exists()),
+    }
+}
+
+// lines of text drawn over the game on a translucent box
+pub struct Text<'ttf> {
+    font: Font<'ttf, 'static>,
+}
+
+impl<'ttf> Text<'ttf> {
+    pub fn load(ttf: &'ttf Sdl2TtfContext, path: &Path) -> Result<Self, String> {
+        let font = ttf.load_font(path, FONT_SIZE)?;
+        Ok(Self { font })
+    }
+
+    pub fn line_height(&self) -> i32 {
+        self.font.recommended_line_spacing()
+    }
+
+    // the size the lines take up, box included
+    pub fn size(&self, lines: &[String]) -> (u32, u32) {
+        let width = lines
+            .iter()
+            .filter_map(|line| self.font.size_of(line).ok())
+            .map(|(w, _)| w)
+            .max()
+            .unwrap_or(0);
+        let height = self.line_height() as u32 * lines.len() as u32;
+        (width + MARGIN as u32, height + MARGIN as u32)
+    }
+
+    pub fn draw(
+        &self,
+        canvas: &mut Canvas<Window>,
+        creator: &TextureCreator<WindowContext>,
+        lines: &[String],
+        x: i32,
+        y: i32,
+    ) -> Result<(), String> {
+        let (width, height) = self.size(lines);
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 176));
+        canvas.fill_rect(Rect::new(x, y, width, height))?;
+
+        let mut line_y = y + MARGIN / 2;
+        for line in lines {
+            // SDL_ttf won't render an empty string
+            if !line.is_empty() {
+                let surface = self
+                    .font
+                    .render(line)
+                    .blended(Color::WHITE)
+                    .map_err(|e| e.to_string())?;
+                let texture = creator
+                    .create_texture_from_surface(&surface)
+                    .map_err(|e| e.to_string())?;
+                let target = Rect::new(x + MARGIN / 2, line_y, surface.width(), surface.height());
+                canvas.copy(&texture, None, target)?;
+            }
+            line_y += self.line_height();
+        }
+        Ok(())
+    }
+}
+
+// short messages confirming what a hotkey did. Without a font they go to stdout instead
+pub struct Notifier {
+    on_screen: bool,
+    message: Option<(String, Instant)>,
+}
+
+impl Notifier {
+    pub fn new(on_screen: bool) -> Self {
+        Self {
+            on_screen,
+            message: None,
+        }
+    }
+
+    pub fn show(&mut self, message: String) {
+        if !self.on_screen {
+            println!("{}", message);
+            return;
+        }
+        self.message = Some((message, Instant::now()));
+    }
+
+    // in the bottom left corner until it times out
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        creator: &TextureCreator<WindowContext>,
+        text: &Text,
+    ) -> Result<(), String> {
+        if matches!(&self.message, Some((_, at)) if at.elapsed() > SHOW_FOR) {
+            self.message = None;
+        }
+        let Some((message, _)) = &self.message else {
+            return Ok(());
+        };
+        let lines = [message.clone()];
+        let (_, height) = text.size(&lines);
+        let (_, window_height) = canvas.output_size()?;
+        text.draw(
+            canvas,
+            creator,
+            &lines,
+            MARGIN,
+            window_height as i32 - height as i32 - MARGIN,
+        )
+    }
+}
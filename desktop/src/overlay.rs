@@ -0,0 +1,264 @@
+use crate::osd::{Text, MARGIN};
+use crate::session::Snapshot;
+use chip8_core::disasm;
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+use sdl2::VideoSubsystem;
+use std::time::{Duration, Instant};
+
+const WINDOW_WIDTH: u32 = 900;
+const WINDOW_HEIGHT: u32 = 480;
+const DISASM_LINES: usize = 20;
+const MEMORY_ROWS: usize = 16;
+const MEMORY_ROW: usize = 16;
+const NO_FONT: &str = "The debug views need a font, pass --font";
+
+// what the game is doing, drawn over it, plus an optional second window with memory and
+// breakpoints
+pub struct Overlay {
+    has_font: bool,
+    visible: bool,
+    rate: Rate,
+    window: Option<MemoryWindow>,
+}
+
+// instructions per second, measured over the last second
+struct Rate {
+    since: Instant,
+    executed_at: u64,
+    per_second: u64,
+}
+
+impl Rate {
+    fn update(&mut self, executed: u64) {
+        let elapsed = self.since.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.per_second = ((executed - self.executed_at) as f64 / elapsed.as_secs_f64()) as u64;
+            self.since = Instant::now();
+            self.executed_at = executed;
+        }
+    }
+}
+
+struct MemoryWindow {
+    canvas: Canvas<Window>,
+    creator: TextureCreator<WindowContext>,
+    // where the disassembly was last drawn, so clicks can find the line under the mouse
+    disasm_addrs: Vec<u16>,
+    disasm_top: i32,
+    disasm_width: i32,
+    line_height: i32,
+}
+
+impl Overlay {
+    pub fn new(has_font: bool) -> Self {
+        Self {
+            has_font,
+            visible: false,
+            rate: Rate {
+                since: Instant::now(),
+                executed_at: 0,
+                per_second: 0,
+            },
+            window: None,
+        }
+    }
+
+    pub fn toggle(&mut self) -> String {
+        if !self.has_font {
+            return NO_FONT.to_string();
+        }
+        self.visible = !self.visible;
+        if self.visible {
+            "Overlay on"
+        } else {
+            "Overlay off"
+        }
+        .to_string()
+    }
+
+    pub fn toggle_window(&mut self, video: &VideoSubsystem) -> String {
+        if !self.has_font {
+            return NO_FONT.to_string();
+        }
+        if self.window.take().is_some() {
+            return "Memory window closed".to_string();
+        }
+        match MemoryWindow::open(video) {
+            Ok(window) => {
+                self.window = Some(window);
+                "Memory window opened, click an instruction to toggle a breakpoint".to_string()
+            }
+            Err(e) => format!("Unable to open the memory window: {}", e),
+        }
+    }
+
+    pub fn window_id(&self) -> Option<u32> {
+        self.window.as_ref().map(|w| w.canvas.window().id())
+    }
+
+    pub fn close_window(&mut self) {
+        self.window = None;
+    }
+
+    // the instruction under a click in the memory window
+    pub fn breakpoint_at(&self, x: i32, y: i32) -> Option<u16> {
+        let window = self.window.as_ref()?;
+        if x >= window.disasm_width || y < window.disasm_top {
+            return None;
+        }
+        let line = (y - window.disasm_top) / window.line_height;
+        window.disasm_addrs.get(line as usize).copied()
+    }
+
+    // over the game in the top left corner, if it's showing
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        creator: &TextureCreator<WindowContext>,
+        text: &Text,
+        snapshot: &Snapshot,
+    ) -> Result<(), String> {
+        self.rate.update(snapshot.executed);
+        if !self.visible {
+            return Ok(());
+        }
+
+        let state = &snapshot.state;
+        let v = &state.v_reg;
+        let mut lines: Vec<String> = (0..4)
+            .map(|row| {
+                (0..4)
+                    .map(|col| format!("V{:X} {:02X}", row * 4 + col, v[row * 4 + col]))
+                    .collect::<Vec<_>>()
+                    .join("  ")
+            })
+            .collect();
+        lines.push(format!(
+            "PC {:03X}  I {:03X}  DT {:02X}  ST {:02X}",
+            state.pc, state.i_reg, state.dt, state.st
+        ));
+        let stack: Vec<String> = state.stack[..state.sp as usize]
+            .iter()
+            .rev()
+            .map(|addr| format!("{:03X}", addr))
+            .collect();
+        lines.push(format!("Stack {}", stack.join(" ")));
+        lines.push(match disasm::disassemble(&state.ram, state.pc, 1).first() {
+            Some(line) => line.to_string(),
+            None => format!("{:03X}: out of memory", state.pc),
+        });
+        lines.push(format!(
+            "{} instructions/s{}",
+            self.rate.per_second,
+            if snapshot.paused { ", paused" } else { "" }
+        ));
+
+        text.draw(canvas, creator, &lines, MARGIN, MARGIN)
+    }
+
+    pub fn draw_window(&mut self, text: &Text, snapshot: &Snapshot) -> Result<(), String> {
+        match &mut self.window {
+            Some(window) => window.draw(text, snapshot),
+            None => Ok(()),
+        }
+    }
+}
+
+impl MemoryWindow {
+    fn open(video: &VideoSubsystem) -> Result<Self, String> {
+        let window = video
+            .window("Chip-8 Memory", WINDOW_WIDTH, WINDOW_HEIGHT)
+            .resizable()
+            .build()
+            .map_err(|e| e.to_string())?;
+        // no vsync, the game window already waits for that
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+        let creator = canvas.texture_creator();
+        Ok(Self {
+            canvas,
+            creator,
+            disasm_addrs: Vec::new(),
+            disasm_top: 0,
+            disasm_width: 0,
+            line_height: 1,
+        })
+    }
+
+    fn draw(&mut self, text: &Text, snapshot: &Snapshot) -> Result<(), String> {
+        let state = &snapshot.state;
+        let pc = state.pc;
+        // keep pc a third of the way down so there's more of what's coming than what's been
+        let start = pc.saturating_sub((DISASM_LINES / 3) as u16 * 2);
+        let disasm = disasm::disassemble(&state.ram, start, DISASM_LINES);
+        self.disasm_addrs = disasm.iter().map(|line| line.addr).collect();
+        let disasm: Vec<String> = disasm
+            .iter()
+            .map(|line| {
+                let marker = match (line.addr == pc, snapshot.breakpoints.contains(&line.addr)) {
+                    (true, true) => "*>",
+                    (true, false) => " >",
+                    (false, true) => "* ",
+                    (false, false) => "  ",
+                };
+                format!("{}{}", marker, line)
+            })
+            .collect();
+
+        // the 16 bytes from I are what FX55, FX65 and DXYN work on
+        let ram = &state.ram;
+        let i = state.i_reg as usize;
+        let mem_start = (i / MEMORY_ROW).saturating_sub(1) * MEMORY_ROW;
+        let mut memory = vec![format!("Memory from {:03X}, I = {:03X}", mem_start, i)];
+        memory.extend(
+            (0..MEMORY_ROWS)
+                .map(|row| mem_start + row * MEMORY_ROW)
+                .take_while(|&addr| addr < ram.len())
+                .map(|addr| {
+                    let bytes: Vec<String> = ram[addr..(addr + MEMORY_ROW).min(ram.len())]
+                        .iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect();
+                    format!("{:03X}: {}", addr, bytes.join(" "))
+                }),
+        );
+
+        let breakpoints: Vec<String> = snapshot
+            .breakpoints
+            .iter()
+            .map(|addr| format!("{:03X}", addr))
+            .collect();
+        let breakpoints = [if breakpoints.is_empty() {
+            "No breakpoints, click an instruction to set one".to_string()
+        } else {
+            format!("Breakpoints {}", breakpoints.join(" "))
+        }];
+
+        self.canvas.set_draw_color(Color::RGB(24, 24, 24));
+        self.canvas.clear();
+        let (disasm_width, disasm_height) = text.size(&disasm);
+        let (_, memory_height) = text.size(&memory);
+        text.draw(&mut self.canvas, &self.creator, &disasm, MARGIN, MARGIN)?;
+        text.draw(
+            &mut self.canvas,
+            &self.creator,
+            &memory,
+            MARGIN * 2 + disasm_width as i32,
+            MARGIN,
+        )?;
+        text.draw(
+            &mut self.canvas,
+            &self.creator,
+            &breakpoints,
+            MARGIN,
+            MARGIN * 2 + disasm_height.max(memory_height) as i32,
+        )?;
+        self.canvas.present();
+
+        self.disasm_top = MARGIN + MARGIN / 2;
+        self.disasm_width = MARGIN + disasm_width as i32;
+        self.line_height = text.line_height().max(1);
+        Ok(())
+    }
+}
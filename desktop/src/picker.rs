@@ -0,0 +1,140 @@
+use crate::library::Entry;
+use crate::osd::{Text, MARGIN};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::render::{Canvas, TextureCreator};
+use sdl2::video::{Window, WindowContext};
+use sdl2::EventPump;
+use std::path::PathBuf;
+
+const HELP: &str = "Pick a rom with the arrow keys and Enter, or drop one on the window";
+
+// lets the player choose from the library in the game window. Returns None if they quit instead
+pub fn pick(
+    event_pump: &mut EventPump,
+    canvas: &mut Canvas<Window>,
+    creator: &TextureCreator<WindowContext>,
+    text: Option<&Text>,
+    entries: &[Entry],
+) -> Result<Option<PathBuf>, String> {
+    if text.is_none() {
+        // nothing can be drawn without a font, so the list goes to the terminal
+        println!("No font for the rom picker, drop a rom on the window or run with one of:");
+        for entry in entries {
+            println!("  {}", entry.path.display());
+        }
+    }
+
+    let mut selected: usize = 0;
+    let mut first_shown: usize = 0;
+    loop {
+        // nothing moves on its own here, so wait for something to happen
+        let mut events: Vec<Event> = event_pump.wait_event_timeout(250).into_iter().collect();
+        events.extend(event_pump.poll_iter());
+
+        let rows = visible_rows(canvas, text);
+        for evt in events {
+            match evt {
+                Event::Quit { .. }
+                | Event::Window {
+                    win_event: WindowEvent::Close,
+                    ..
+                }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return Ok(None),
+                Event::DropFile { filename, .. } => return Ok(Some(PathBuf::from(filename))),
+                Event::KeyDown {
+                    keycode: Some(key), ..
+                } if !entries.is_empty() => match key {
+                    Keycode::Up => selected = selected.saturating_sub(1),
+                    Keycode::Down => selected = (selected + 1).min(entries.len() - 1),
+                    Keycode::PageUp => selected = selected.saturating_sub(rows),
+                    Keycode::PageDown => selected = (selected + rows).min(entries.len() - 1),
+                    Keycode::Home => selected = 0,
+                    Keycode::End => selected = entries.len() - 1,
+                    Keycode::Return | Keycode::KpEnter => {
+                        return Ok(Some(entries[selected].path.clone()))
+                    }
+                    _ => (),
+                },
+                Event::MouseWheel { y, .. } if !entries.is_empty() => {
+                    selected = (selected as i32 - y).clamp(0, entries.len() as i32 - 1) as usize;
+                }
+                // a click selects and a double click plays
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    clicks,
+                    y,
+                    ..
+                } => {
+                    if let Some(text) = text {
+                        let row = (y - list_top(text)).div_euclid(text.line_height());
+                        let clicked = first_shown as i32 + row;
+                        if row >= 0 && clicked < entries.len() as i32 {
+                            selected = clicked as usize;
+                            if clicks >= 2 {
+                                return Ok(Some(entries[selected].path.clone()));
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        // scroll just enough to keep the selection in view
+        if selected < first_shown {
+            first_shown = selected;
+        } else if selected >= first_shown + rows {
+            first_shown = selected + 1 - rows;
+        }
+
+        canvas.set_draw_color(Color::RGB(24, 24, 24));
+        canvas.clear();
+        if let Some(text) = text {
+            let mut lines = vec![HELP.to_string(), String::new()];
+            if entries.is_empty() {
+                lines.push("No roms found, add directories with --rom-dir".to_string());
+            }
+            lines.extend(
+                entries
+                    .iter()
+                    .enumerate()
+                    .skip(first_shown)
+                    .take(rows)
+                    .map(|(i, entry)| describe(entry, i == selected)),
+            );
+            text.draw(canvas, creator, &lines, MARGIN, MARGIN)?;
+        }
+        canvas.present();
+    }
+}
+
+fn describe(entry: &Entry, selected: bool) -> String {
+    let mut line = format!("{} {}", if selected { ">" } else { " " }, entry.title);
+    if let Some(platform) = &entry.platform {
+        line += &format!("  [{}]", platform);
+    }
+    if entry.recent {
+        line += "  (recent)";
+    }
+    line
+}
+
+// where the first entry is drawn, below the help and a blank line
+fn list_top(text: &Text) -> i32 {
+    MARGIN + MARGIN / 2 + text.line_height() * 2
+}
+
+fn visible_rows(canvas: &Canvas<Window>, text: Option<&Text>) -> usize {
+    let Some(text) = text else {
+        return 1;
+    };
+    let (_, height) = canvas.output_size().unwrap_or((0, 0));
+    let rows = (height as i32 - list_top(text) - MARGIN) / text.line_height();
+    rows.max(1) as usize
+}
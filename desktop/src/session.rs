@@ -0,0 +1,332 @@
+use chip8_core::debug::{Debugger, StopReason, Watch};
+use chip8_core::runner::Machine;
+use chip8_core::{rom_hash, Emu, SaveState};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+pub const NUM_SLOTS: u8 = 10;
+// how many frames run per displayed frame while fast-forwarding
+const FAST_FORWARD: usize = 4;
+// what speed up and speed down step through, in instructions per frame
+const SPEEDS: [usize; 14] = [1, 2, 3, 5, 7, 10, 15, 20, 30, 50, 100, 200, 500, 1000];
+// how far run_to goes looking for its address, a minute of play
+const RUN_TO_FRAMES: usize = 60 * 60;
+
+// how to pick up again after reloading a rom that changed
+#[derive(Clone, Debug, Default)]
+pub struct Reload {
+    // keep holding whatever keys were held
+    pub keep_keys: bool,
+    // restore this instead of starting from scratch
+    pub state: Option<SaveState>,
+    // run until pc gets here, then pause
+    pub run_to: Option<u16>,
+}
+
+// what the ui thread gets to see of the session after each frame
+pub struct Snapshot {
+    // why the session just stopped, if it did
+    pub message: Option<String>,
+    // the registers and memory, for the debug views
+    pub state: SaveState,
+    pub breakpoints: Vec<u16>,
+    pub paused: bool,
+    pub executed: u64,
+}
+
+// the running game and the controls the hotkeys act on. It lives on the emulation thread and
+// every action returns the message to show the player
+pub struct Session {
+    pub chip8: Emu,
+    pub debugger: Debugger,
+    rom: Vec<u8>,
+    hash: String,
+    pub ticks_per_frame: usize,
+    pub paused: bool,
+    pub fast_forward: bool,
+    pub slot: u8,
+    // instructions run so far, for measuring speed
+    executed: u64,
+}
+
+impl Session {
+    pub fn new(chip8: Emu, rom: Vec<u8>, ticks_per_frame: usize) -> Self {
+        Self {
+            chip8,
+            debugger: Debugger::new(),
+            hash: rom_hash(&rom),
+            rom,
+            ticks_per_frame,
+            paused: false,
+            fast_forward: false,
+            slot: 0,
+            executed: 0,
+        }
+    }
+
+    // runs one displayed frame's worth of emulation, unless paused. Hitting a breakpoint or a
+    // fault pauses and says why
+    fn play(&mut self) -> Option<String> {
+        if self.paused {
+            return None;
+        }
+        let frames = if self.fast_forward { FAST_FORWARD } else { 1 };
+        for _ in 0..frames {
+            if let Some(reason) = self.run_frame() {
+                self.paused = true;
+                return Some(describe(reason));
+            }
+        }
+        None
+    }
+
+    fn run_frame(&mut self) -> Option<StopReason> {
+        let reason = self
+            .debugger
+            .run_frame(&mut self.chip8, self.ticks_per_frame);
+        if reason.is_none() {
+            self.executed += self.ticks_per_frame as u64;
+        }
+        reason
+    }
+
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> String {
+        let set = self.debugger.toggle_breakpoint(addr);
+        format!(
+            "Breakpoint at {:03X} {}",
+            addr,
+            if set { "set" } else { "cleared" }
+        )
+    }
+
+    pub fn toggle_pause(&mut self) -> String {
+        self.paused = !self.paused;
+        if self.paused { "Paused" } else { "Resumed" }.to_string()
+    }
+
+    // a hard reset, as if the rom had just been loaded
+    pub fn reset(&mut self) -> String {
+        self.chip8.reset();
+        let loaded = self.chip8.load(&self.rom);
+        self.debugger.sync(&self.chip8);
+        match loaded {
+            Ok(()) => "Reset".to_string(),
+            Err(e) => format!("Reset failed: {}", e),
+        }
+    }
+
+    // swaps in a new build of the rom and starts it over. Anything in reload that's set carries
+    // the session on from where it was rather than from a clean start
+    pub fn reload(&mut self, rom: Vec<u8>, reload: &Reload) -> String {
+        let keys = *self.chip8.keys();
+        self.hash = rom_hash(&rom);
+        self.rom = rom;
+        self.chip8.reset();
+        if let Err(e) = self.chip8.load(&self.rom) {
+            return format!("Reload failed: {}", e);
+        }
+
+        // the state's registers, screen and data with the new build's code written back over it
+        if let Some(state) = &reload.state {
+            let restored = self
+                .chip8
+                .load_state(state)
+                .map_err(|e| e.to_string())
+                .and_then(|()| {
+                    let start = self.chip8.config().start_addr as usize;
+                    self.chip8
+                        .write_ram(start, &self.rom)
+                        .map_err(|e| e.to_string())
+                });
+            if let Err(e) = restored {
+                return format!("Reloaded but the state couldn't be restored: {}", e);
+            }
+        }
+        if reload.keep_keys {
+            for (key, &pressed) in keys.iter().enumerate() {
+                self.chip8.keypress(key, pressed);
+            }
+        }
+        self.debugger.sync(&self.chip8);
+
+        match reload.run_to {
+            Some(addr) => self.run_to(addr),
+            None => "Reloaded".to_string(),
+        }
+    }
+
+    // runs flat out until pc reaches addr, then pauses there
+    fn run_to(&mut self, addr: u16) -> String {
+        let temporary = !self.debugger.has_breakpoint(addr);
+        self.debugger.set_breakpoint(addr);
+        let mut reason = None;
+        for _ in 0..RUN_TO_FRAMES {
+            reason = self.run_frame();
+            if reason.is_some() {
+                break;
+            }
+        }
+        if temporary {
+            self.debugger.clear_breakpoint(addr);
+        }
+
+        self.paused = true;
+        match reason {
+            Some(StopReason::Breakpoint(at)) if at == addr => {
+                format!("Reloaded and ran to {:03X}", addr)
+            }
+            Some(reason) => describe(reason),
+            None => format!("Reloaded but didn't reach {:03X}", addr),
+        }
+    }
+
+    // single steps pause first, so the instruction run is the one shown
+    pub fn step(&mut self) -> String {
+        self.paused = true;
+        match self.debugger.step(&mut self.chip8) {
+            Some(reason) => describe(reason),
+            None => format!("Stepped to {:03X}", self.chip8.pc()),
+        }
+    }
+
+    pub fn advance_frame(&mut self) -> String {
+        self.paused = true;
+        match self.run_frame() {
+            Some(reason) => describe(reason),
+            None => "Advanced one frame".to_string(),
+        }
+    }
+
+    pub fn toggle_fast_forward(&mut self) -> String {
+        self.fast_forward = !self.fast_forward;
+        if self.fast_forward {
+            format!("Fast forward x{}", FAST_FORWARD)
+        } else {
+            "Normal speed".to_string()
+        }
+    }
+
+    pub fn speed_up(&mut self) -> String {
+        if let Some(&speed) = SPEEDS.iter().find(|&&s| s > self.ticks_per_frame) {
+            self.ticks_per_frame = speed;
+        }
+        self.speed_message()
+    }
+
+    pub fn speed_down(&mut self) -> String {
+        if let Some(&speed) = SPEEDS.iter().rev().find(|&&s| s < self.ticks_per_frame) {
+            self.ticks_per_frame = speed;
+        }
+        self.speed_message()
+    }
+
+    fn speed_message(&self) -> String {
+        format!("{} instructions per frame", self.ticks_per_frame)
+    }
+
+    pub fn next_slot(&mut self) -> String {
+        self.slot = (self.slot + 1) % NUM_SLOTS;
+        self.slot_message()
+    }
+
+    pub fn prev_slot(&mut self) -> String {
+        self.slot = (self.slot + NUM_SLOTS - 1) % NUM_SLOTS;
+        self.slot_message()
+    }
+
+    fn slot_message(&self) -> String {
+        let used = self.slot_path().map(|p| p.exists()).unwrap_or(false);
+        format!("Slot {}{}", self.slot, if used { "" } else { " (empty)" })
+    }
+
+    pub fn quick_save(&mut self) -> String {
+        match self.save_slot() {
+            Ok(()) => format!("Saved slot {}", self.slot),
+            Err(e) => format!("Saving slot {} failed: {}", self.slot, e),
+        }
+    }
+
+    pub fn quick_load(&mut self) -> String {
+        match self.load_slot() {
+            Ok(()) => format!("Loaded slot {}", self.slot),
+            Err(e) => format!("Loading slot {} failed: {}", self.slot, e),
+        }
+    }
+
+    fn save_slot(&self) -> Result<(), Box<dyn Error>> {
+        let path = self.slot_path().ok_or("no data directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.chip8.save_state().to_bytes())?;
+        Ok(())
+    }
+
+    fn load_slot(&mut self) -> Result<(), Box<dyn Error>> {
+        let path = self.slot_path().ok_or("no data directory")?;
+        let data = fs::read(path).map_err(|_| "slot is empty")?;
+        self.chip8.load_state(&SaveState::from_bytes(&data)?)?;
+        self.debugger.sync(&self.chip8);
+        Ok(())
+    }
+
+    // slots belong to the rom rather than the file, so renaming or moving it keeps its saves
+    fn slot_path(&self) -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| {
+            dir.join("chip8")
+                .join("states")
+                .join(format!("{}-{}.state", self.hash, self.slot))
+        })
+    }
+}
+
+impl Machine for Session {
+    type Report = Snapshot;
+
+    fn emu(&self) -> &Emu {
+        &self.chip8
+    }
+
+    fn emu_mut(&mut self) -> &mut Emu {
+        &mut self.chip8
+    }
+
+    fn frame(&mut self) -> Snapshot {
+        let message = self.play();
+        Snapshot {
+            message,
+            state: self.chip8.save_state(),
+            breakpoints: self.debugger.breakpoints().collect(),
+            paused: self.paused,
+            executed: self.executed,
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    fn is_sounding(&self) -> bool {
+        self.chip8.is_sounding() && !self.paused
+    }
+
+    fn reloaded(&mut self) {
+        self.debugger.sync(&self.chip8);
+    }
+}
+
+fn describe(reason: StopReason) -> String {
+    match reason {
+        StopReason::Breakpoint(addr) => format!("Breakpoint at {:03X}", addr),
+        StopReason::Watch { watch, old, new } => {
+            let name = match watch {
+                Watch::Memory(addr) => format!("[{:03X}]", addr),
+                Watch::Register(x) => format!("V{:X}", x),
+                Watch::I => "I".to_string(),
+            };
+            format!("{} changed from {:X} to {:X}", name, old, new)
+        }
+        StopReason::Fault(fault) => format!("Fault: {}", fault),
+    }
+}
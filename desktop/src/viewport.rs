@@ -0,0 +1,24 @@
+use chip8_core::{HIRES_HEIGHT, HIRES_WIDTH};
+use sdl2::rect::Rect;
+
+// where the game goes in a window of any size: as big as fits at 2:1 and centred, with bars
+// either side or above and below. Both resolutions are 2:1, so switching between them never
+// moves the game. With integer scaling the size is a whole multiple of the hi-res grid, which
+// keeps lo-res pixels whole too
+pub fn game_rect((width, height): (u32, u32), integer_scale: bool) -> Rect {
+    let (grid_width, grid_height) = (HIRES_WIDTH as u32, HIRES_HEIGHT as u32);
+    let (game_width, game_height) = if integer_scale {
+        let scale = (width / grid_width).min(height / grid_height).max(1);
+        (grid_width * scale, grid_height * scale)
+    } else if width * grid_height > height * grid_width {
+        (height * grid_width / grid_height, height)
+    } else {
+        (width, width * grid_height / grid_width)
+    };
+    Rect::new(
+        (width as i32 - game_width as i32) / 2,
+        (height as i32 - game_height as i32) / 2,
+        game_width.max(1),
+        game_height.max(1),
+    )
+}
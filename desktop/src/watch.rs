@@ -0,0 +1,61 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+// how often the file is looked at
+const POLL_EVERY: Duration = Duration::from_millis(250);
+
+// notices when the rom file is rewritten. Assemblers don't always write a file in one go, so a
+// change only counts once the file has looked the same for a whole poll
+pub struct RomWatcher {
+    path: PathBuf,
+    last_poll: Instant,
+    // modification time and size when the rom was last loaded
+    loaded: Option<(SystemTime, u64)>,
+    // what was seen at the previous poll, if it differed from what's loaded
+    changing: Option<(SystemTime, u64)>,
+}
+
+impl RomWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            last_poll: Instant::now(),
+            loaded: stamp(path),
+            changing: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // true once a change has settled and the rom should be reloaded
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_EVERY {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        // a missing file is usually one that's in the middle of being replaced
+        let Some(now) = stamp(&self.path) else {
+            return false;
+        };
+        if Some(now) == self.loaded {
+            self.changing = None;
+            return false;
+        }
+        if self.changing == Some(now) {
+            self.loaded = Some(now);
+            self.changing = None;
+            return true;
+        }
+        self.changing = Some(now);
+        false
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
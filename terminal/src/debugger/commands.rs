@@ -0,0 +1,110 @@
+use chip8_core::debug::Watch;
+use std::path::PathBuf;
+
+pub const HELP: &str = "step [n] | continue | break <addr> | watch <addr|vX|i> | unwatch <addr|vX|i> \
+| poke <addr> <byte>.. | poke <vX|i|pc> <value> | mem [addr] | load-state <file> | save-state <file> \
+| reset | quit. Numbers are hex";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    // toggles
+    Break(u16),
+    Watch(Watch),
+    Unwatch(Watch),
+    PokeMemory(u16, Vec<u8>),
+    PokeRegister(Register, u16),
+    // pins the memory view to an address, or back to following I
+    Mem(Option<u16>),
+    LoadState(PathBuf),
+    SaveState(PathBuf),
+    Reset,
+    Help,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Register {
+    V(u8),
+    I,
+    Pc,
+}
+
+pub fn parse(line: &str) -> Result<Command, String> {
+    let mut words = line.split_whitespace();
+    let name = words.next().ok_or("no command")?;
+    let args: Vec<&str> = words.collect();
+
+    let command = match (name, args.as_slice()) {
+        ("s" | "step", []) => Command::Step(1),
+        ("s" | "step", [n]) => Command::Step(n.parse().map_err(|_| format!("bad count {}", n))?),
+        ("c" | "continue", []) => Command::Continue,
+        ("b" | "break", [addr]) => Command::Break(parse_hex(addr)?),
+        ("w" | "watch", [target]) => Command::Watch(parse_watch(target)?),
+        ("unwatch", [target]) => Command::Unwatch(parse_watch(target)?),
+        ("p" | "poke", [target, values @ ..]) if !values.is_empty() => {
+            match parse_register(target) {
+                // V registers are a byte, I and PC take the full value
+                Some(reg @ Register::V(_)) if values.len() == 1 => {
+                    Command::PokeRegister(reg, to_byte(parse_hex(values[0])?)?.into())
+                }
+                Some(reg) if values.len() == 1 => Command::PokeRegister(reg, parse_hex(values[0])?),
+                Some(_) => return Err("registers take one value".to_string()),
+                None => Command::PokeMemory(
+                    parse_hex(target)?,
+                    values
+                        .iter()
+                        .map(|v| parse_hex(v).and_then(to_byte))
+                        .collect::<Result<_, _>>()?,
+                ),
+            }
+        }
+        ("m" | "mem", []) => Command::Mem(None),
+        ("m" | "mem", [addr]) => Command::Mem(Some(parse_hex(addr)?)),
+        ("load-state", [path]) => Command::LoadState(PathBuf::from(path)),
+        ("save-state", [path]) => Command::SaveState(PathBuf::from(path)),
+        ("reset", []) => Command::Reset,
+        ("h" | "help", []) => Command::Help,
+        ("q" | "quit", []) => Command::Quit,
+        _ => return Err(format!("don't understand '{}', try help", line.trim())),
+    };
+
+    Ok(command)
+}
+
+fn parse_hex(s: &str) -> Result<u16, String> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix('$'))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("bad number {}", s))
+}
+
+fn to_byte(val: u16) -> Result<u8, String> {
+    u8::try_from(val).map_err(|_| format!("{:X} doesn't fit in a byte", val))
+}
+
+fn parse_register(s: &str) -> Option<Register> {
+    let lower = s.to_ascii_lowercase();
+    match lower.as_str() {
+        "i" => Some(Register::I),
+        "pc" => Some(Register::Pc),
+        _ => {
+            let x = lower.strip_prefix('v')?;
+            if x.len() != 1 {
+                return None;
+            }
+            u8::from_str_radix(x, 16).ok().map(Register::V)
+        }
+    }
+}
+
+fn parse_watch(s: &str) -> Result<Watch, String> {
+    match parse_register(s) {
+        Some(Register::V(x)) => Ok(Watch::Register(x)),
+        Some(Register::I) => Ok(Watch::I),
+        Some(Register::Pc) => Err("use a breakpoint to stop on pc".to_string()),
+        None => Ok(Watch::Memory(parse_hex(s)?)),
+    }
+}
@@ -0,0 +1,255 @@
+use crate::input::Keypad;
+use chip8_core::debug::{Debugger, StopReason, Watch};
+use chip8_core::render::Palette;
+use chip8_core::{Emu, SaveState};
+use commands::{Command, Register};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::backend::CrosstermBackend;
+use ratatui::Terminal;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+mod commands;
+mod ui;
+
+const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / 60);
+
+// full screen debugger. While running the keyboard goes to the keypad and Esc pauses, while
+// paused it goes to the command line
+pub struct App {
+    chip8: Emu,
+    debugger: Debugger,
+    keypad: Keypad,
+    palette: Palette,
+    rom: Vec<u8>,
+    ticks_per_frame: usize,
+    running: bool,
+    command: String,
+    last_command: String,
+    message: String,
+    // memory view pinned to an address, otherwise it follows I
+    mem_addr: Option<u16>,
+    quit: bool,
+}
+
+pub fn run(
+    chip8: Emu,
+    rom: Vec<u8>,
+    keypad: Keypad,
+    palette: Palette,
+    ticks_per_frame: usize,
+) -> Result<(), Box<dyn Error>> {
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.clear()?;
+
+    let mut app = App {
+        chip8,
+        debugger: Debugger::new(),
+        keypad,
+        palette,
+        rom,
+        ticks_per_frame,
+        running: false,
+        command: String::new(),
+        last_command: String::new(),
+        message: "Paused. F5 continues, F10 steps, type help for commands".to_string(),
+        mem_addr: None,
+        quit: false,
+    };
+
+    let mut next_frame = Instant::now();
+    while !app.quit {
+        terminal.draw(|frame| ui::draw(frame, &app))?;
+
+        let timeout = next_frame.saturating_duration_since(Instant::now());
+        if event::poll(timeout)? {
+            if let Event::Key(evt) = event::read()? {
+                app.handle_key(&evt);
+            }
+        }
+
+        let now = Instant::now();
+        if now < next_frame {
+            continue;
+        }
+        next_frame = if now > next_frame + FRAME_TIME {
+            now + FRAME_TIME
+        } else {
+            next_frame + FRAME_TIME
+        };
+
+        if app.running {
+            for (key, pressed) in app.keypad.update(now).into_iter().enumerate() {
+                app.chip8.keypress(key, pressed);
+            }
+            if let Some(reason) = app.debugger.run_frame(&mut app.chip8, app.ticks_per_frame) {
+                app.stop(reason);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl App {
+    fn handle_key(&mut self, evt: &KeyEvent) {
+        if evt.code == KeyCode::Char('c') && evt.modifiers.contains(KeyModifiers::CONTROL) {
+            self.quit = true;
+            return;
+        }
+
+        if self.running {
+            if evt.code == KeyCode::Esc && evt.kind == KeyEventKind::Press {
+                self.pause("Paused");
+            } else {
+                self.keypad.handle(evt, Instant::now());
+            }
+            return;
+        }
+
+        if evt.kind == KeyEventKind::Release {
+            return;
+        }
+        match evt.code {
+            KeyCode::F(5) => self.execute(Command::Continue),
+            KeyCode::F(10) => self.execute(Command::Step(1)),
+            KeyCode::Char(c) => self.command.push(c),
+            KeyCode::Backspace => {
+                self.command.pop();
+            }
+            KeyCode::Esc => self.command.clear(),
+            KeyCode::Enter => {
+                // an empty line repeats the last command, handy for stepping
+                let line = if self.command.trim().is_empty() {
+                    self.last_command.clone()
+                } else {
+                    std::mem::take(&mut self.command)
+                };
+                if line.is_empty() {
+                    return;
+                }
+                match commands::parse(&line) {
+                    Ok(command) => self.execute(command),
+                    Err(e) => self.message = e,
+                }
+                self.last_command = line;
+            }
+            _ => (),
+        }
+    }
+
+    fn execute(&mut self, command: Command) {
+        match command {
+            Command::Step(n) => {
+                let mut reason = None;
+                for _ in 0..n {
+                    reason = self.debugger.step(&mut self.chip8);
+                    if reason.is_some() {
+                        break;
+                    }
+                }
+                match reason {
+                    Some(reason) => self.stop(reason),
+                    None => self.message = format!("Stepped to {:03X}", self.chip8.pc()),
+                }
+            }
+            Command::Continue => {
+                self.running = true;
+                self.message = "Running, Esc pauses".to_string();
+            }
+            Command::Break(addr) => {
+                let set = self.debugger.toggle_breakpoint(addr);
+                self.message = format!(
+                    "Breakpoint at {:03X} {}",
+                    addr,
+                    if set { "set" } else { "cleared" }
+                );
+            }
+            Command::Watch(watch) => {
+                self.debugger.watch(&self.chip8, watch);
+                self.message = format!("Watching {}", watch_name(watch));
+            }
+            Command::Unwatch(watch) => {
+                self.message = if self.debugger.unwatch(watch) {
+                    format!("Stopped watching {}", watch_name(watch))
+                } else {
+                    format!("{} wasn't watched", watch_name(watch))
+                };
+            }
+            Command::PokeMemory(addr, bytes) => {
+                self.message = match self.chip8.write_ram(addr as usize, &bytes) {
+                    Ok(()) => format!("Wrote {} bytes at {:03X}", bytes.len(), addr),
+                    Err(e) => e.to_string(),
+                };
+                self.debugger.sync(&self.chip8);
+            }
+            Command::PokeRegister(reg, val) => {
+                match reg {
+                    Register::V(x) => self.chip8.set_v_reg(x as usize, val as u8),
+                    Register::I => self.chip8.set_i_reg(val),
+                    Register::Pc => self.chip8.set_pc(val),
+                }
+                self.debugger.sync(&self.chip8);
+                self.message = "Register set".to_string();
+            }
+            Command::Mem(addr) => self.mem_addr = addr,
+            Command::LoadState(path) => {
+                let result = fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| SaveState::from_bytes(&data).map_err(|e| e.to_string()))
+                    .and_then(|state| self.chip8.load_state(&state).map_err(|e| e.to_string()));
+                self.message = match result {
+                    Ok(()) => format!("Loaded {}", path.display()),
+                    Err(e) => e,
+                };
+                self.debugger.sync(&self.chip8);
+            }
+            Command::SaveState(path) => {
+                self.message = match fs::write(&path, self.chip8.save_state().to_bytes()) {
+                    Ok(()) => format!("Saved {}", path.display()),
+                    Err(e) => e.to_string(),
+                };
+            }
+            Command::Reset => {
+                self.chip8.reset();
+                self.message = match self.chip8.load(&self.rom) {
+                    Ok(()) => "Reset".to_string(),
+                    Err(e) => e.to_string(),
+                };
+                self.debugger.sync(&self.chip8);
+            }
+            Command::Help => self.message = commands::HELP.to_string(),
+            Command::Quit => self.quit = true,
+        }
+    }
+
+    fn stop(&mut self, reason: StopReason) {
+        let message = match reason {
+            StopReason::Breakpoint(addr) => format!("Breakpoint at {:03X}", addr),
+            StopReason::Watch { watch, old, new } => {
+                format!("{} changed from {:X} to {:X}", watch_name(watch), old, new)
+            }
+            StopReason::Fault(fault) => format!("Fault: {}", fault),
+        };
+        self.pause(&message);
+    }
+
+    fn pause(&mut self, message: &str) {
+        self.running = false;
+        self.message = message.to_string();
+        // nothing is held down while the command line has the keyboard
+        for key in 0..16 {
+            self.chip8.keypress(key, false);
+        }
+    }
+}
+
+fn watch_name(watch: Watch) -> String {
+    match watch {
+        Watch::Memory(addr) => format!("[{:03X}]", addr),
+        Watch::Register(x) => format!("V{:X}", x),
+        Watch::I => "I".to_string(),
+    }
+}
@@ -0,0 +1,240 @@
+use super::{watch_name, App};
+use chip8_core::debug::Watch;
+use chip8_core::disasm;
+use chip8_core::render::Rgba;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+const MEM_ROW: usize = 16;
+
+pub fn draw(frame: &mut Frame, app: &App) {
+    let (width, height) = app.chip8.display_size();
+    let [top, middle, memory, command] = Layout::vertical([
+        Constraint::Length(height as u16 / 2 + 2),
+        Constraint::Length(8),
+        Constraint::Min(4),
+        Constraint::Length(3),
+    ])
+    .areas(frame.area());
+    let [display, disassembly] =
+        Layout::horizontal([Constraint::Length(width as u16 + 2), Constraint::Min(24)]).areas(top);
+    let [registers, stack, watches] = Layout::horizontal([
+        Constraint::Length(40),
+        Constraint::Length(14),
+        Constraint::Min(20),
+    ])
+    .areas(middle);
+
+    draw_display(frame, app, display);
+    draw_disassembly(frame, app, disassembly);
+    draw_registers(frame, app, registers);
+    draw_stack(frame, app, stack);
+    draw_watches(frame, app, watches);
+    draw_memory(frame, app, memory);
+    draw_command(frame, app, command);
+}
+
+fn draw_display(frame: &mut Frame, app: &App, area: Rect) {
+    let pixels = app.chip8.get_display();
+    let (width, height) = app.chip8.display_size();
+    let fg = color(app.palette.foreground());
+    let bg = color(app.palette.background());
+    let lines: Vec<Line> = (0..height / 2)
+        .map(|row| {
+            Line::from(
+                (0..width)
+                    .map(|x| {
+                        let top = if pixels[x + width * row * 2] { fg } else { bg };
+                        let bottom = if pixels[x + width * (row * 2 + 1)] {
+                            fg
+                        } else {
+                            bg
+                        };
+                        Span::styled("▀", Style::new().fg(top).bg(bottom))
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+
+    let title = if app.running { "Running" } else { "Paused" };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn draw_disassembly(frame: &mut Frame, app: &App, area: Rect) {
+    let rows = area.height.saturating_sub(2) as usize;
+    let pc = app.chip8.pc();
+    // keep pc a third of the way down so there's more of what's coming than what's been
+    let start = pc.saturating_sub((rows / 3) as u16 * 2);
+
+    let lines: Vec<Line> = disasm::disassemble(app.chip8.ram(), start, rows)
+        .into_iter()
+        .map(|line| {
+            let marker = match (line.addr == pc, app.debugger.has_breakpoint(line.addr)) {
+                (true, true) => "●▶",
+                (true, false) => " ▶",
+                (false, true) => "● ",
+                (false, false) => "  ",
+            };
+            let style = if line.addr == pc {
+                Style::new().add_modifier(Modifier::REVERSED)
+            } else if app.debugger.has_breakpoint(line.addr) {
+                Style::new().fg(Color::Red)
+            } else {
+                Style::new()
+            };
+            Line::styled(format!("{}{}", marker, line), style)
+        })
+        .collect();
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Disassembly")),
+        area,
+    );
+}
+
+fn draw_registers(frame: &mut Frame, app: &App, area: Rect) {
+    let v = app.chip8.v_regs();
+    let mut lines: Vec<Line> = (0..4)
+        .map(|row| {
+            Line::from(
+                (0..4)
+                    .map(|col| {
+                        let x = row * 4 + col;
+                        format!("V{:X}={:02X}  ", x, v[x])
+                    })
+                    .collect::<String>(),
+            )
+        })
+        .collect();
+    lines.push(Line::from(format!(
+        "PC={:03X} I={:03X} SP={:X}",
+        app.chip8.pc(),
+        app.chip8.i_reg(),
+        app.chip8.sp()
+    )));
+    lines.push(Line::from(format!(
+        "DT={:02X} ST={:02X}{}",
+        app.chip8.delay_timer(),
+        app.chip8.sound_timer(),
+        if app.chip8.is_waiting_for_key() {
+            " waiting for key"
+        } else {
+            ""
+        }
+    )));
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Registers")),
+        area,
+    );
+}
+
+fn draw_stack(frame: &mut Frame, app: &App, area: Rect) {
+    // newest first
+    let lines: Vec<Line> = app
+        .chip8
+        .stack()
+        .iter()
+        .rev()
+        .map(|addr| Line::from(format!("{:03X}", addr)))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Stack")),
+        area,
+    );
+}
+
+fn draw_watches(frame: &mut Frame, app: &App, area: Rect) {
+    let ram = app.chip8.ram();
+    let mut lines: Vec<Line> = app
+        .debugger
+        .breakpoints()
+        .map(|addr| Line::styled(format!("break {:03X}", addr), Style::new().fg(Color::Red)))
+        .collect();
+    lines.extend(app.debugger.watches().map(|watch| {
+        let value = match watch {
+            Watch::Memory(addr) => ram.get(addr as usize).copied().unwrap_or(0) as u16,
+            Watch::Register(x) => app.chip8.v_regs()[x as usize] as u16,
+            Watch::I => app.chip8.i_reg(),
+        };
+        Line::from(format!("watch {} = {:X}", watch_name(watch), value))
+    }));
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Breakpoints and watches")),
+        area,
+    );
+}
+
+fn draw_memory(frame: &mut Frame, app: &App, area: Rect) {
+    let ram = app.chip8.ram();
+    let i = app.chip8.i_reg() as usize;
+    let rows = area.height.saturating_sub(2) as usize;
+    let focus = app.mem_addr.map(|a| a as usize).unwrap_or(i);
+    // start a row above the one being looked at
+    let start = (focus / MEM_ROW).saturating_sub(1) * MEM_ROW;
+    let highlight = Style::new().fg(Color::Black).bg(Color::Yellow);
+
+    let lines: Vec<Line> = (0..rows)
+        .map(|row| start + row * MEM_ROW)
+        .take_while(|&addr| addr < ram.len())
+        .map(|addr| {
+            let mut spans = vec![Span::raw(format!("{:03X}: ", addr))];
+            for (offset, byte) in ram[addr..(addr + MEM_ROW).min(ram.len())]
+                .iter()
+                .enumerate()
+            {
+                // I usually points at a sprite or a block of registers, so mark the 16 bytes
+                // from I
+                let style = if (i..i + MEM_ROW).contains(&(addr + offset)) {
+                    highlight
+                } else {
+                    Style::new()
+                };
+                spans.push(Span::styled(format!("{:02X}", byte), style));
+                spans.push(Span::raw(" "));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let title = match app.mem_addr {
+        Some(addr) => format!("Memory at {:03X}", addr),
+        None => "Memory at I".to_string(),
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn draw_command(frame: &mut Frame, app: &App, area: Rect) {
+    let prompt = if app.running {
+        Line::from(app.message.as_str())
+    } else {
+        Line::from(vec![
+            Span::raw("> "),
+            Span::raw(app.command.as_str()),
+            Span::styled("█", Style::new().add_modifier(Modifier::SLOW_BLINK)),
+        ])
+    };
+    let title = if app.running {
+        String::new()
+    } else {
+        app.message.clone()
+    };
+    frame.render_widget(
+        Paragraph::new(prompt).block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+fn color(c: Rgba) -> Color {
+    Color::Rgb(c[0], c[1], c[2])
+}
@@ -0,0 +1,84 @@
+use chip8_core::keymap::Keymap;
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyEventState};
+use std::time::{Duration, Instant};
+
+const NUM_KEYS: usize = 16;
+
+// tracks the keypad from terminal key events. Most terminals only send presses (and repeats
+// while a key is held), so unless real release events are available a key counts as released
+// once no press has been seen for the timeout
+pub struct Keypad {
+    keymap: Keymap,
+    release_after: Duration,
+    // whether the terminal reports releases, in which case the timeout isn't used
+    has_release_events: bool,
+    pressed_at: [Option<Instant>; NUM_KEYS],
+}
+
+impl Keypad {
+    pub fn new(keymap: Keymap, release_after: Duration, has_release_events: bool) -> Self {
+        Self {
+            keymap,
+            release_after,
+            has_release_events,
+            pressed_at: [None; NUM_KEYS],
+        }
+    }
+
+    // returns the chip-8 key the event was for, if any
+    pub fn handle(&mut self, evt: &KeyEvent, now: Instant) -> Option<usize> {
+        let key = self.keymap.key(&key_code(evt)?)?;
+        self.pressed_at[key] = match evt.kind {
+            KeyEventKind::Press | KeyEventKind::Repeat => Some(now),
+            KeyEventKind::Release => None,
+        };
+        Some(key)
+    }
+
+    // releases any key that timed out and reports the state of every key
+    pub fn update(&mut self, now: Instant) -> [bool; NUM_KEYS] {
+        let mut keys = [false; NUM_KEYS];
+        for (key, pressed_at) in self.pressed_at.iter_mut().enumerate() {
+            if let Some(at) = *pressed_at {
+                if !self.has_release_events && now.duration_since(at) > self.release_after {
+                    *pressed_at = None;
+                } else {
+                    keys[key] = true;
+                }
+            }
+        }
+        keys
+    }
+}
+
+// terminals report characters rather than physical keys, so this guesses the KeyboardEvent.code
+// name the keymap uses from the character. Shifted symbols aren't mapped back to their keys, and
+// the numpad is only told apart when the terminal reports it
+fn key_code(evt: &KeyEvent) -> Option<String> {
+    let numpad = evt.state.contains(KeyEventState::KEYPAD);
+    let code = match evt.code {
+        KeyCode::Char(c) if numpad => match c {
+            '0'..='9' => return Some(format!("Numpad{}", c)),
+            '/' => "NumpadDivide",
+            '*' => "NumpadMultiply",
+            '-' => "NumpadSubtract",
+            '+' => "NumpadAdd",
+            '.' => "NumpadDecimal",
+            _ => return None,
+        },
+        KeyCode::Enter if numpad => "NumpadEnter",
+        KeyCode::Char(c) if c.is_ascii_alphabetic() => {
+            return Some(format!("Key{}", c.to_ascii_uppercase()))
+        }
+        KeyCode::Char(c) if c.is_ascii_digit() => return Some(format!("Digit{}", c)),
+        KeyCode::Char(' ') => "Space",
+        KeyCode::Enter => "Enter",
+        KeyCode::Tab => "Tab",
+        KeyCode::Up => "ArrowUp",
+        KeyCode::Down => "ArrowDown",
+        KeyCode::Left => "ArrowLeft",
+        KeyCode::Right => "ArrowRight",
+        _ => return None,
+    };
+    Some(code.to_string())
+}
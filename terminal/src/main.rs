@@ -0,0 +1,173 @@
+use chip8_core::keymap::Keymap;
+use chip8_core::render::Palette;
+use chip8_core::*;
+use clap::Parser;
+use crossterm::event::{
+    self, Event, KeyCode, KeyModifiers, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+    PushKeyboardEnhancementFlags,
+};
+use crossterm::style::ResetColor;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute};
+use input::Keypad;
+use screen::{Mode, Screen};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+mod debugger;
+mod input;
+mod screen;
+
+const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / 60);
+
+#[derive(Parser)]
+#[command(name = "chip8-term", about = "Run a chip-8 rom in the terminal")]
+struct Args {
+    rom: PathBuf,
+    /// Open the debugger instead of just playing
+    #[arg(long)]
+    debug: bool,
+    /// Draw with braille characters instead of half blocks
+    #[arg(long)]
+    braille: bool,
+    #[arg(long, default_value_t = 10)]
+    ticks_per_frame: usize,
+    /// One of classic, amber, green or high-contrast
+    #[arg(long, default_value = "classic")]
+    palette: String,
+    /// One of qwerty, azerty or numpad, or a .toml or .json keymap file
+    #[arg(long, default_value = "qwerty")]
+    keymap: String,
+    /// How long a key stays down after the terminal last reported it, in milliseconds. Not used
+    /// by terminals that report key releases
+    #[arg(long, default_value_t = 150)]
+    release_ms: u64,
+}
+
+// puts the terminal back the way it was even if the emulator panics
+struct TerminalGuard {
+    enhanced_keyboard: bool,
+}
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut out = io::stdout();
+        execute!(out, EnterAlternateScreen, cursor::Hide)?;
+
+        let enhanced_keyboard = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if enhanced_keyboard {
+            execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )?;
+        }
+
+        Ok(Self { enhanced_keyboard })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        if self.enhanced_keyboard {
+            execute!(out, PopKeyboardEnhancementFlags).ok();
+        }
+        execute!(out, ResetColor, cursor::Show, LeaveAlternateScreen).ok();
+        terminal::disable_raw_mode().ok();
+    }
+}
+
+fn main() {
+    let args = Args::parse();
+    if let Err(e) = run(args) {
+        eprintln!("chip8-term: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let palette = Palette::preset(&args.palette)
+        .ok_or_else(|| format!("unknown palette {}", args.palette))?;
+    let keymap = match Keymap::preset(&args.keymap) {
+        Some(keymap) => keymap,
+        None => Keymap::load(Path::new(&args.keymap))
+            .map_err(|e| format!("keymap {}: {}", args.keymap, e))?,
+    };
+    let mode = if args.braille {
+        Mode::Braille
+    } else {
+        Mode::HalfBlock
+    };
+
+    let rom = fs::read(&args.rom)?;
+    let mut chip8 = Emu::new();
+    chip8.load(&rom)?;
+
+    let guard = TerminalGuard::enter()?;
+    let mut keypad = Keypad::new(
+        keymap,
+        Duration::from_millis(args.release_ms),
+        guard.enhanced_keyboard,
+    );
+    if args.debug {
+        return debugger::run(chip8, rom, keypad, palette, args.ticks_per_frame);
+    }
+    let mut screen = Screen::new(mode, palette);
+    let mut out = io::BufWriter::new(io::stdout());
+
+    let mut next_frame = Instant::now();
+    'gameloop: loop {
+        // wait for input until the next frame is due
+        loop {
+            let now = Instant::now();
+            if now >= next_frame {
+                break;
+            }
+            if !event::poll(next_frame - now)? {
+                continue;
+            }
+            match event::read()? {
+                Event::Key(evt) => {
+                    let ctrl_c = evt.code == KeyCode::Char('c')
+                        && evt.modifiers.contains(KeyModifiers::CONTROL);
+                    if evt.code == KeyCode::Esc || ctrl_c {
+                        break 'gameloop;
+                    }
+                    keypad.handle(&evt, Instant::now());
+                }
+                Event::Resize(..) => {
+                    execute!(out, terminal::Clear(terminal::ClearType::All))?;
+                    screen.invalidate();
+                }
+                _ => (),
+            }
+        }
+        // don't try to catch up after the terminal was suspended or stalled
+        let now = Instant::now();
+        next_frame = if now > next_frame + FRAME_TIME {
+            now + FRAME_TIME
+        } else {
+            next_frame + FRAME_TIME
+        };
+
+        for (key, pressed) in keypad.update(Instant::now()).into_iter().enumerate() {
+            chip8.keypress(key, pressed);
+        }
+        chip8.run_frame(args.ticks_per_frame);
+        let (width, height) = chip8.display_size();
+        screen.draw(&mut out, chip8.get_display(), width, height)?;
+    }
+
+    out.flush()?;
+    drop(guard);
+
+    if let Some(fault) = chip8.fault() {
+        eprintln!("chip8-term: {}", fault);
+    }
+    Ok(())
+}
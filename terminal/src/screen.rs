@@ -0,0 +1,127 @@
+use chip8_core::render::{Palette, Rgba};
+use crossterm::cursor::MoveTo;
+use crossterm::queue;
+use crossterm::style::{Color, Colors, Print, SetColors};
+use crossterm::terminal::{Clear, ClearType};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // one character per 1x2 pixels using '▀' with the top pixel as foreground, so each pixel
+    // keeps its own colour
+    HalfBlock,
+    // one character per 2x4 pixels, a quarter of the size but only one colour per cell
+    Braille,
+}
+
+impl Mode {
+    // characters needed to show a display of this size
+    pub fn cells(&self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Mode::HalfBlock => (width, height.div_ceil(2)),
+            Mode::Braille => (width.div_ceil(2), height.div_ceil(4)),
+        }
+    }
+}
+
+pub struct Screen {
+    mode: Mode,
+    palette: Palette,
+    // only redraw when the display actually changed
+    last: Vec<bool>,
+}
+
+impl Screen {
+    pub fn new(mode: Mode, palette: Palette) -> Self {
+        Self {
+            mode,
+            palette,
+            last: Vec::new(),
+        }
+    }
+
+    pub fn invalidate(&mut self) {
+        self.last.clear();
+    }
+
+    pub fn draw<W: Write>(
+        &mut self,
+        out: &mut W,
+        pixels: &[bool],
+        width: usize,
+        height: usize,
+    ) -> io::Result<()> {
+        if self.last == pixels {
+            return Ok(());
+        }
+        // going back to lo-res would otherwise leave the rest of the hi-res picture behind
+        if !self.last.is_empty() && self.last.len() != pixels.len() {
+            queue!(out, Clear(ClearType::All))?;
+        }
+
+        let (cols, rows) = self.mode.cells(width, height);
+        let lit = |x: usize, y: usize| x < width && y < height && pixels[x + width * y];
+        let fg = color(self.palette.foreground());
+        let bg = color(self.palette.background());
+
+        for row in 0..rows {
+            queue!(out, MoveTo(0, row as u16))?;
+            let mut line = String::with_capacity(cols * 4);
+            let mut current = None;
+            for col in 0..cols {
+                let (colors, ch) = match self.mode {
+                    Mode::HalfBlock => {
+                        let top = if lit(col, row * 2) { fg } else { bg };
+                        let bottom = if lit(col, row * 2 + 1) { fg } else { bg };
+                        (Colors::new(top, bottom), '▀')
+                    }
+                    Mode::Braille => {
+                        let mut dots = 0u32;
+                        for (bit, (dx, dy)) in BRAILLE_DOTS.iter().enumerate() {
+                            if lit(col * 2 + dx, row * 4 + dy) {
+                                dots |= 1 << bit;
+                            }
+                        }
+                        let ch = char::from_u32(0x2800 + dots).unwrap_or(' ');
+                        (Colors::new(fg, bg), ch)
+                    }
+                };
+
+                // colours are only sent when they change, which keeps each frame small enough
+                // to be usable over ssh
+                if current != Some(colors) {
+                    queue!(out, Print(&line), SetColors(colors))?;
+                    line.clear();
+                    current = Some(colors);
+                }
+                line.push(ch);
+            }
+            queue!(out, Print(&line))?;
+        }
+        out.flush()?;
+
+        self.last.clear();
+        self.last.extend_from_slice(pixels);
+        Ok(())
+    }
+}
+
+// braille dot bit order, as (x, y) inside the 2x4 cell
+const BRAILLE_DOTS: [(usize, usize); 8] = [
+    (0, 0),
+    (0, 1),
+    (0, 2),
+    (1, 0),
+    (1, 1),
+    (1, 2),
+    (0, 3),
+    (1, 3),
+];
+
+fn color(c: Rgba) -> Color {
+    Color::Rgb {
+        r: c[0],
+        g: c[1],
+        b: c[2],
+    }
+}
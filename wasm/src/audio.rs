@@ -0,0 +1,163 @@
+use chip8_core::audio::{DEFAULT_FREQUENCY, DEFAULT_VOLUME, FRAMES_PER_SECOND};
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use web_sys::{AudioContext, AudioContextState, GainNode, OscillatorNode, OscillatorType};
+
+const FRAME_SECONDS: f64 = 1.0 / FRAMES_PER_SECOND as f64;
+// the gain eases to each new level over a few of these, quick enough to keep beeps crisp but
+// not so quick that they click
+const GAIN_TIME_CONSTANT: f64 = 0.001;
+// frames scheduled further ahead than this are dropped back to now, so the sound can't drift
+// behind the picture
+const MAX_LATENCY_SECONDS: f64 = 0.1;
+
+// the buzzer as a square wave oscillator that's always running, switched on and off with a gain
+// node by the sound timer. Browsers won't make any sound until a page does something on a click or
+// key press, so nothing is created until enable() is called from one
+pub(crate) struct WebAudio {
+    volume: f32,
+    muted: bool,
+    sounding: bool,
+    nodes: Option<Nodes>,
+}
+
+struct Nodes {
+    ctx: AudioContext,
+    gain: GainNode,
+    // kept so it isn't collected while playing
+    _oscillator: OscillatorNode,
+    // on the audio clock, when the next frame's sound starts
+    next_time: f64,
+}
+
+impl WebAudio {
+    pub fn new() -> Self {
+        Self {
+            volume: DEFAULT_VOLUME,
+            muted: false,
+            sounding: false,
+            nodes: None,
+        }
+    }
+
+    // creates the audio context if need be and resumes it. Has to be called from a user gesture
+    // the first time
+    pub fn enable(&mut self) -> Result<Promise, JsValue> {
+        if self.nodes.is_none() {
+            self.nodes = Some(Nodes::new()?);
+            self.apply();
+        }
+        let nodes = self.nodes.as_ref().ok_or("no audio context")?;
+        nodes.ctx.resume()
+    }
+
+    // suspended until enabled from a user gesture, then running
+    pub fn state(&self) -> &'static str {
+        match self.nodes.as_ref().map(|n| n.ctx.state()) {
+            Some(AudioContextState::Running) => "running",
+            Some(AudioContextState::Closed) => "closed",
+            _ => "suspended",
+        }
+    }
+
+    // called once per 60Hz frame with whether the sound timer is running. Frames are run in
+    // bursts off requestAnimationFrame, so each one gets its own 1/60s on the audio clock rather
+    // than all starting now, which keeps a one frame beep one frame long
+    pub fn frame(&mut self, sounding: bool) {
+        let level = self.level(sounding);
+        let changed = sounding != self.sounding;
+        self.sounding = sounding;
+        let Some(nodes) = self.nodes.as_mut() else {
+            return;
+        };
+        let now = nodes.ctx.current_time();
+        let time = if nodes.next_time < now || nodes.next_time > now + MAX_LATENCY_SECONDS {
+            now
+        } else {
+            nodes.next_time
+        };
+        nodes.next_time = time + FRAME_SECONDS;
+        if changed {
+            nodes.set_level(level, time);
+        }
+    }
+
+    // stops the sound straight away, for when frames stop coming
+    pub fn silence(&mut self) {
+        self.sounding = false;
+        self.apply();
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    // 0 to 1
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.apply();
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply();
+    }
+
+    fn level(&self, sounding: bool) -> f32 {
+        if sounding && !self.muted {
+            self.volume
+        } else {
+            0.0
+        }
+    }
+
+    // sets the gain for the current state now, dropping anything still scheduled
+    fn apply(&mut self) {
+        let level = self.level(self.sounding);
+        if let Some(nodes) = self.nodes.as_mut() {
+            let now = nodes.ctx.current_time();
+            nodes.gain.gain().cancel_scheduled_values(now).ok();
+            nodes.next_time = now;
+            nodes.set_level(level, now);
+        }
+    }
+}
+
+impl Drop for WebAudio {
+    fn drop(&mut self) {
+        if let Some(nodes) = &self.nodes {
+            nodes.ctx.close().ok();
+        }
+    }
+}
+
+impl Nodes {
+    fn new() -> Result<Self, JsValue> {
+        let ctx = AudioContext::new()?;
+        let gain = ctx.create_gain()?;
+        gain.gain().set_value(0.0);
+        gain.connect_with_audio_node(&ctx.destination())?;
+        let oscillator = ctx.create_oscillator()?;
+        oscillator.set_type(OscillatorType::Square);
+        oscillator.frequency().set_value(DEFAULT_FREQUENCY);
+        oscillator.connect_with_audio_node(&gain)?;
+        oscillator.start()?;
+        Ok(Self {
+            ctx,
+            gain,
+            _oscillator: oscillator,
+            next_time: 0.0,
+        })
+    }
+
+    fn set_level(&self, level: f32, time: f64) {
+        self.gain
+            .gain()
+            .set_target_at_time(level, time, GAIN_TIME_CONSTANT)
+            .ok();
+    }
+}
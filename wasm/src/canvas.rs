@@ -0,0 +1,42 @@
+use crate::EmuWasm;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::{Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+// draws an emulator on a canvas the page hands over, for pages that don't want to deal with the
+// framebuffer themselves. The canvas is kept at the display's size, so give it a CSS size and
+// image-rendering: pixelated to scale it up
+#[wasm_bindgen]
+pub struct CanvasScreen {
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+}
+
+#[wasm_bindgen]
+impl CanvasScreen {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement) -> Result<CanvasScreen, JsValue> {
+        let ctx = canvas
+            .get_context("2d")?
+            .ok_or("canvas has no 2d context")?
+            .dyn_into::<CanvasRenderingContext2d>()?;
+        Ok(CanvasScreen { canvas, ctx })
+    }
+
+    #[wasm_bindgen]
+    pub fn draw(&self, emu: &mut EmuWasm) -> Result<(), JsValue> {
+        emu.render();
+        let state = emu.state.borrow();
+        let (width, height) = state.chip8.display_size();
+        if self.canvas.width() != width as u32 || self.canvas.height() != height as u32 {
+            self.canvas.set_width(width as u32);
+            self.canvas.set_height(height as u32);
+        }
+        let image = ImageData::new_with_u8_clamped_array_and_sh(
+            Clamped(state.renderer.buffer()),
+            width as u32,
+            height as u32,
+        )?;
+        self.ctx.put_image_data(&image, 0.0, 0.0)
+    }
+}
@@ -0,0 +1,154 @@
+// the registers, memory and breakpoints for a debugger panel on the page. Breakpoints are
+// checked by every frame run, from the main loop or run_frame(), and hitting one pauses
+
+use crate::{to_js, EmuWasm};
+use chip8_core::debug::{StopReason, Watch};
+use chip8_core::disasm::disassemble;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+impl EmuWasm {
+    #[wasm_bindgen]
+    pub fn pc(&self) -> u16 {
+        self.state.borrow().chip8.pc()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_pc(&mut self, pc: u16) {
+        self.state.borrow_mut().chip8.set_pc(pc);
+    }
+
+    #[wasm_bindgen]
+    pub fn i_reg(&self) -> u16 {
+        self.state.borrow().chip8.i_reg()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_i_reg(&mut self, i: u16) {
+        self.state.borrow_mut().chip8.set_i_reg(i);
+    }
+
+    // V0 to VF
+    #[wasm_bindgen]
+    pub fn v_regs(&self) -> Vec<u8> {
+        self.state.borrow().chip8.v_regs().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn set_v_reg(&mut self, x: usize, value: u8) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        if x >= state.chip8.v_regs().len() {
+            return Err(JsValue::from_str(&format!("no register V{:X}", x)));
+        }
+        state.chip8.set_v_reg(x, value);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn sp(&self) -> u16 {
+        self.state.borrow().chip8.sp()
+    }
+
+    // only the return addresses pushed so far, oldest first
+    #[wasm_bindgen]
+    pub fn stack(&self) -> Vec<u16> {
+        self.state.borrow().chip8.stack().to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn delay_timer(&self) -> u8 {
+        self.state.borrow().chip8.delay_timer()
+    }
+
+    #[wasm_bindgen]
+    pub fn sound_timer(&self) -> u8 {
+        self.state.borrow().chip8.sound_timer()
+    }
+
+    #[wasm_bindgen]
+    pub fn memory_size(&self) -> usize {
+        self.state.borrow().chip8.ram().len()
+    }
+
+    // a copy of len bytes from start, cut short at the end of memory
+    #[wasm_bindgen]
+    pub fn memory(&self, start: usize, len: usize) -> Vec<u8> {
+        let state = self.state.borrow();
+        let ram = state.chip8.ram();
+        let start = start.min(ram.len());
+        let end = start.saturating_add(len).min(ram.len());
+        ram[start..end].to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn poke(&mut self, addr: usize, data: &[u8]) -> Result<(), JsValue> {
+        self.state
+            .borrow_mut()
+            .chip8
+            .write_ram(addr, data)
+            .map_err(to_js)
+    }
+
+    #[wasm_bindgen]
+    pub fn set_breakpoint(&mut self, addr: u16) {
+        self.state.borrow_mut().debugger.set_breakpoint(addr);
+    }
+
+    // returns whether there was one
+    #[wasm_bindgen]
+    pub fn clear_breakpoint(&mut self, addr: u16) -> bool {
+        self.state.borrow_mut().debugger.clear_breakpoint(addr)
+    }
+
+    // returns whether the breakpoint is now set
+    #[wasm_bindgen]
+    pub fn toggle_breakpoint(&mut self, addr: u16) -> bool {
+        self.state.borrow_mut().debugger.toggle_breakpoint(addr)
+    }
+
+    #[wasm_bindgen]
+    pub fn breakpoints(&self) -> Vec<u16> {
+        self.state.borrow().debugger.breakpoints().collect()
+    }
+
+    // pauses and runs exactly one instruction. Returns why it stopped if it faulted
+    #[wasm_bindgen]
+    pub fn step(&mut self) -> Option<String> {
+        let state = &mut *self.state.borrow_mut();
+        state.paused = true;
+        let reason = state.debugger.step(&mut state.chip8).map(describe);
+        state.stop_reason.clone_from(&reason);
+        reason
+    }
+
+    // why the last frame paused, a breakpoint or a fault, until it's unpaused
+    #[wasm_bindgen]
+    pub fn stop_reason(&self) -> Option<String> {
+        self.state.borrow().stop_reason.clone()
+    }
+
+    // count lines from start, each like "200: 00E0  CLS", two bytes apart
+    #[wasm_bindgen]
+    pub fn disassemble(&self, start: u16, count: usize) -> Vec<String> {
+        let state = self.state.borrow();
+        disassemble(state.chip8.ram(), start, count)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+}
+
+pub(crate) fn describe(reason: StopReason) -> String {
+    match reason {
+        StopReason::Breakpoint(addr) => format!("Breakpoint at {:03X}", addr),
+        StopReason::Watch { watch, old, new } => {
+            let name = match watch {
+                Watch::Memory(addr) => format!("[{:03X}]", addr),
+                Watch::Register(x) => format!("V{:X}", x),
+                Watch::I => "I".to_string(),
+            };
+            format!("{} changed from {:X} to {:X}", name, old, new)
+        }
+        StopReason::Fault(fault) => format!("Fault: {}", fault),
+    }
+}
@@ -1,105 +1,472 @@
+use chip8_core::debug::Debugger;
+use chip8_core::keymap::Keymap;
+use chip8_core::render::{Palette, Renderer, NUM_COLORS};
+use chip8_core::romdb::{RomDb, RomInfo};
 use chip8_core::*;
-use js_sys::Uint8Array;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
-use web_sys::{console, CanvasRenderingContext2d, HtmlCanvasElement, KeyboardEvent};
 
+#[cfg(feature = "audio")]
+mod audio;
+#[cfg(feature = "canvas")]
+mod canvas;
+mod debug;
+#[cfg(feature = "fetch")]
+mod library;
+#[cfg(feature = "main-loop")]
+mod main_loop;
+#[cfg(feature = "storage")]
+mod storage;
+
+#[cfg(feature = "canvas")]
+pub use canvas::CanvasScreen;
+
+pub const DEFAULT_TICKS_PER_FRAME: usize = 10;
+
+// the emulator without any page around it. Nothing here looks for a window, document or canvas,
+// so any number can run side by side, in a worker or under Node. Only start() needs a browser
 #[wasm_bindgen]
 pub struct EmuWasm {
+    // shared with the main loop's animation frame callback
+    state: Rc<RefCell<State>>,
+    #[cfg(feature = "main-loop")]
+    main_loop: Option<main_loop::MainLoop>,
+}
+
+struct State {
     chip8: Emu,
-    ctx: CanvasRenderingContext2d,
+    keymap: Keymap,
+    // the RGBA framebuffer, one pixel per chip-8 pixel. Pages scale it up with CSS
+    renderer: Renderer,
+    ticks_per_frame: usize,
+    paused: bool,
+    // sha1 of the loaded rom, which save states are stored under
+    rom_hash: Option<String>,
+    debugger: Debugger,
+    // why the emulator last paused itself
+    stop_reason: Option<String>,
+    romdb: RomDb,
+    #[cfg(feature = "fetch")]
+    library: Vec<library::LibraryRom>,
+    #[cfg(feature = "audio")]
+    audio: audio::WebAudio,
+}
+
+impl State {
+    // roms the database knows are played with their platform's quirks and at their own speed
+    fn load(&mut self, data: &[u8]) -> Result<(), LoadError> {
+        let info = self.romdb.lookup(data);
+        self.chip8
+            .set_quirks(info.and_then(RomInfo::quirks).unwrap_or_default());
+        if let Some(tickrate) = info.and_then(|info| info.tickrate) {
+            self.ticks_per_frame = tickrate;
+        }
+        self.chip8.load(data)?;
+        self.rom_hash = Some(rom_hash(data));
+        self.stop_reason = None;
+        Ok(())
+    }
+
+    // hitting a breakpoint or a fault pauses partway through the frame and says why
+    fn run_frame(&mut self, ticks_per_frame: usize) {
+        match self.debugger.run_frame(&mut self.chip8, ticks_per_frame) {
+            Some(reason) => {
+                self.paused = true;
+                self.stop_reason = Some(debug::describe(reason));
+            }
+            None => self.timers_ticked(),
+        }
+    }
+
+    fn tick_timers(&mut self) {
+        self.chip8.tick_timers();
+        self.timers_ticked();
+    }
+
+    // the buzzer follows the sound timer a frame at a time
+    fn timers_ticked(&mut self) {
+        #[cfg(feature = "audio")]
+        self.audio.frame(self.chip8.is_sounding());
+    }
 }
 
 #[wasm_bindgen]
 impl EmuWasm {
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Result<EmuWasm, JsValue> {
-        let chip8 = Emu::new();
-        console::log_1(&"new".into());
-        let document = web_sys::window().unwrap().document().unwrap();
-        let canvas = document.get_element_by_id("canvas").unwrap();
-        let canvas: HtmlCanvasElement = canvas
-            .dyn_into::<HtmlCanvasElement>()
-            .map_err(|_| ())
-            .unwrap();
-
-        let ctx = canvas
-            .get_context("2d")
-            .unwrap()
-            .unwrap()
-            .dyn_into::<CanvasRenderingContext2d>()
-            .unwrap();
-
-        Ok(EmuWasm { chip8, ctx })
+    pub fn new() -> EmuWasm {
+        let state = State {
+            chip8: Emu::new(),
+            keymap: Keymap::qwerty(),
+            renderer: Renderer::new(Palette::default()),
+            ticks_per_frame: DEFAULT_TICKS_PER_FRAME,
+            paused: false,
+            rom_hash: None,
+            debugger: Debugger::new(),
+            stop_reason: None,
+            romdb: RomDb::new(),
+            #[cfg(feature = "fetch")]
+            library: Vec::new(),
+            #[cfg(feature = "audio")]
+            audio: audio::WebAudio::new(),
+        };
+        EmuWasm {
+            state: Rc::new(RefCell::new(state)),
+            #[cfg(feature = "main-loop")]
+            main_loop: None,
+        }
     }
 
     #[wasm_bindgen]
     pub fn tick(&mut self) {
-        println!("tick");
-        self.chip8.tick();
-        console::log_1(&"tick".into());
+        self.state.borrow_mut().chip8.tick();
     }
 
     #[wasm_bindgen]
     pub fn tick_timers(&mut self) {
-        self.chip8.tick_timers();
+        self.state.borrow_mut().tick_timers();
     }
 
+    // ticks_per_frame instructions and then the timers, one 60Hz frame. Stops early at a
+    // breakpoint
     #[wasm_bindgen]
-    pub fn reset(&mut self) {
-        self.chip8.reset();
+    pub fn run_frame(&mut self, ticks_per_frame: usize) {
+        self.state.borrow_mut().run_frame(ticks_per_frame);
+    }
+
+    // starts running the game at 60Hz off requestAnimationFrame. on_frame, if given, is called
+    // with the number of frames run whenever any have, which is when to draw
+    #[cfg(feature = "main-loop")]
+    #[wasm_bindgen]
+    pub fn start(&mut self, on_frame: Option<js_sys::Function>) -> Result<(), JsValue> {
+        let state = &self.state;
+        self.main_loop
+            .get_or_insert_with(|| main_loop::MainLoop::new(Rc::clone(state)))
+            .start(on_frame)
+    }
+
+    #[cfg(feature = "main-loop")]
+    #[wasm_bindgen]
+    pub fn stop(&mut self) {
+        if let Some(main_loop) = &self.main_loop {
+            main_loop.stop();
+        }
+        #[cfg(feature = "audio")]
+        self.state.borrow_mut().audio.silence();
+    }
+
+    #[cfg(feature = "main-loop")]
+    #[wasm_bindgen]
+    pub fn is_running(&self) -> bool {
+        self.main_loop.as_ref().is_some_and(|l| l.is_running())
     }
 
+    // a paused main loop keeps going but doesn't run any frames
     #[wasm_bindgen]
-    pub fn keypress(&mut self, evt: KeyboardEvent, pressed: bool) {
-        let key = evt.key();
-        if let Some(k) = key2btn(&key) {
-            self.chip8.keypress(k, pressed);
+    pub fn set_paused(&mut self, paused: bool) {
+        let state = &mut *self.state.borrow_mut();
+        state.paused = paused;
+        if !paused {
+            state.stop_reason = None;
         }
+        #[cfg(feature = "audio")]
+        if paused {
+            state.audio.silence();
+        }
+    }
+
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().paused
     }
 
+    // pauses and runs a single frame
     #[wasm_bindgen]
-    pub fn load_game(&mut self, data: Uint8Array) {
-        self.chip8.load(&data.to_vec());
+    pub fn advance_frame(&mut self) {
+        let state = &mut *self.state.borrow_mut();
+        state.paused = true;
+        state.run_frame(state.ticks_per_frame);
+        // one frame's worth of sound and no more
+        #[cfg(feature = "audio")]
+        state.audio.frame(false);
     }
 
+    // instructions per 60Hz frame in the main loop, which sets the game's speed
     #[wasm_bindgen]
-    pub fn draw_screen(&mut self, scale: usize) {
-        let disp = self.chip8.get_display();
-        console::log_1(&"drawing".into());
-        for i in 0..(SCREEN_WIDTH * SCREEN_HEIGHT) {
-            if disp[i] {
-                let x = i % SCREEN_WIDTH;
-                let y = i / SCREEN_WIDTH;
-                self.ctx.fill_rect(
-                    (x * scale) as f64,
-                    (y * scale) as f64,
-                    scale as f64,
-                    scale as f64,
-                );
+    pub fn set_ticks_per_frame(&mut self, ticks_per_frame: usize) {
+        self.state.borrow_mut().ticks_per_frame = ticks_per_frame;
+    }
+
+    #[wasm_bindgen]
+    pub fn ticks_per_frame(&self) -> usize {
+        self.state.borrow().ticks_per_frame
+    }
+
+    #[wasm_bindgen]
+    pub fn reset(&mut self) {
+        self.state.borrow_mut().chip8.reset();
+    }
+
+    #[wasm_bindgen]
+    pub fn load_game(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        self.state.borrow_mut().load(data).map_err(to_js)
+    }
+
+    // the CHIP-8 community database's programs.json, so load_game() can pick quirks and speed
+    #[wasm_bindgen]
+    pub fn load_romdb_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.state.borrow_mut().romdb = RomDb::from_json(json).map_err(to_js)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn rom_hash(&self) -> Option<String> {
+        self.state.borrow().rom_hash.clone()
+    }
+
+    // key is 0 to F on the chip-8 keypad
+    #[wasm_bindgen]
+    pub fn press_key(&mut self, key: usize, pressed: bool) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        if key >= state.chip8.keys().len() {
+            return Err(JsValue::from_str(&format!("no key {:X}", key)));
+        }
+        state.chip8.keypress(key, pressed);
+        Ok(())
+    }
+
+    // goes by KeyboardEvent.code, the physical key, so shift and the keyboard layout don't
+    // matter. Returns whether the code is mapped, so the page knows to preventDefault
+    #[wasm_bindgen]
+    pub fn press_code(&mut self, code: &str, pressed: bool) -> bool {
+        let state = &mut *self.state.borrow_mut();
+        match state.keymap.key(code) {
+            Some(k) => {
+                state.chip8.keypress(k, pressed);
+                true
             }
+            None => false,
         }
     }
+
+    #[wasm_bindgen]
+    pub fn set_keymap_preset(&mut self, name: &str) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        state.keymap = Keymap::preset(name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown keymap {}", name)))?;
+        Ok(())
+    }
+
+    // custom maps are JSON objects of code to key, e.g. {"KeyQ": 4}
+    #[wasm_bindgen]
+    pub fn set_keymap_json(&mut self, json: &str) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        state.keymap = Keymap::from_json(json).map_err(to_js)?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn keymap_json(&self) -> String {
+        self.state.borrow().keymap.to_json()
+    }
+
+    // one byte per pixel, 1 lit and 0 not, row by row at display_width() wide
+    #[wasm_bindgen]
+    pub fn display(&self) -> Vec<u8> {
+        let state = self.state.borrow();
+        state.chip8.get_display().iter().map(|&p| p as u8).collect()
+    }
+
+    // 64x32, or 128x64 once a rom switches to hi-res
+    #[wasm_bindgen]
+    pub fn display_width(&self) -> usize {
+        self.state.borrow().chip8.display_size().0
+    }
+
+    #[wasm_bindgen]
+    pub fn display_height(&self) -> usize {
+        self.state.borrow().chip8.display_size().1
+    }
+
+    // draws the display into the RGBA framebuffer. The buffer moves when the resolution
+    // changes, so read framebuffer_ptr() again after every render rather than keeping a view
+    // around, e.g.
+    //
+    //     emu.render()
+    //     const pixels = new Uint8ClampedArray(memory.buffer, emu.framebuffer_ptr(), emu.framebuffer_len())
+    //     ctx.putImageData(new ImageData(pixels, emu.display_width()), 0, 0)
+    #[wasm_bindgen]
+    pub fn render(&mut self) {
+        let state = &mut *self.state.borrow_mut();
+        let (width, height) = state.chip8.display_size();
+        state
+            .renderer
+            .render(state.chip8.get_display(), width, height);
+    }
+
+    #[wasm_bindgen]
+    pub fn framebuffer_ptr(&self) -> *const u8 {
+        self.state.borrow().renderer.buffer().as_ptr()
+    }
+
+    // in bytes, four per pixel
+    #[wasm_bindgen]
+    pub fn framebuffer_len(&self) -> usize {
+        self.state.borrow().renderer.buffer().len()
+    }
+
+    // one of classic, amber, green or high-contrast
+    #[wasm_bindgen]
+    pub fn set_palette(&mut self, name: &str) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        state.renderer.palette = Palette::preset(name)
+            .ok_or_else(|| JsValue::from_str(&format!("unknown palette {}", name)))?;
+        Ok(())
+    }
+
+    // colours are 0xRRGGBB. Index 0 is the background and 1 the foreground
+    #[wasm_bindgen]
+    pub fn set_color(&mut self, index: usize, rgb: u32) -> Result<(), JsValue> {
+        let state = &mut *self.state.borrow_mut();
+        let color = state
+            .renderer
+            .palette
+            .colors
+            .get_mut(index)
+            .ok_or_else(|| JsValue::from_str(&format!("no colour {}", index)))?;
+        *color = [(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 0xFF];
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn color(&self, index: usize) -> Option<u32> {
+        let state = self.state.borrow();
+        let [r, g, b, _] = *state.renderer.palette.colors.get(index)?;
+        Some((r as u32) << 16 | (g as u32) << 8 | b as u32)
+    }
+
+    #[wasm_bindgen]
+    pub fn num_colors(&self) -> usize {
+        NUM_COLORS
+    }
+
+    #[wasm_bindgen]
+    pub fn is_sounding(&self) -> bool {
+        self.state.borrow().chip8.is_sounding()
+    }
+
+    // starts the sound, and must be called from a click or key press handler at least once
+    // because browsers keep audio suspended until then. Resolves once sound can play
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn enable_audio(&mut self) -> Result<js_sys::Promise, JsValue> {
+        self.state.borrow_mut().audio.enable()
+    }
+
+    // "suspended" until enable_audio() has worked, then "running"
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn audio_state(&self) -> String {
+        self.state.borrow().audio.state().to_string()
+    }
+
+    // 0 to 1
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn set_volume(&mut self, volume: f32) {
+        self.state.borrow_mut().audio.set_volume(volume);
+    }
+
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn volume(&self) -> f32 {
+        self.state.borrow().audio.volume()
+    }
+
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn set_muted(&mut self, muted: bool) {
+        self.state.borrow_mut().audio.set_muted(muted);
+    }
+
+    #[cfg(feature = "audio")]
+    #[wasm_bindgen]
+    pub fn is_muted(&self) -> bool {
+        self.state.borrow().audio.is_muted()
+    }
+
+    // a Uint8Array in the same format the desktop frontend saves, so states can move between
+    // them
+    #[wasm_bindgen]
+    pub fn save_state(&self) -> Vec<u8> {
+        self.state.borrow().chip8.save_state().to_bytes()
+    }
+
+    #[wasm_bindgen]
+    pub fn load_state(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        let save = SaveState::from_bytes(data).map_err(to_js)?;
+        self.state
+            .borrow_mut()
+            .chip8
+            .load_state(&save)
+            .map_err(to_js)
+    }
+
+    // keeps a save state in localStorage for the loaded rom, one per rom
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn store_state(&self) -> Result<(), JsValue> {
+        let state = self.state.borrow();
+        let hash = state.rom_hash.as_deref().ok_or("no rom is loaded")?;
+        storage::Store::local()?.save_state(hash, &state.chip8.save_state().to_bytes())
+    }
+
+    // loads the stored save state for the loaded rom, returning false if there isn't one
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn restore_state(&mut self) -> Result<bool, JsValue> {
+        let hash = self.rom_hash().ok_or("no rom is loaded")?;
+        match storage::Store::local()?.load_state(&hash)? {
+            Some(data) => self.load_state(&data).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn forget_state(&self) -> Result<(), JsValue> {
+        let hash = self.rom_hash().ok_or("no rom is loaded")?;
+        storage::Store::local()?.remove_state(&hash)
+    }
+
+    // keeps the keymap, palette and speed in localStorage
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn store_settings(&self) -> Result<(), JsValue> {
+        storage::Store::local()?.save_settings(&self.state.borrow())
+    }
+
+    // applies any stored settings, returning whether there were some
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn restore_settings(&mut self) -> Result<bool, JsValue> {
+        storage::Store::local()?.load_settings(&mut self.state.borrow_mut())
+    }
+
+    #[cfg(feature = "storage")]
+    #[wasm_bindgen]
+    pub fn forget_settings(&self) -> Result<(), JsValue> {
+        storage::Store::local()?.clear_settings()
+    }
 }
 
-fn key2btn(key: &str) -> Option<usize> {
-    match key {
-        "1" => Some(0x1),
-        "2" => Some(0x2),
-        "3" => Some(0x3),
-        "4" => Some(0xC),
-        "q" => Some(0x4),
-        "w" => Some(0x5),
-        "e" => Some(0x6),
-        "r" => Some(0xD),
-        "a" => Some(0x7),
-        "s" => Some(0x8),
-        "d" => Some(0x9),
-        "f" => Some(0xE),
-        "z" => Some(0xA),
-        "x" => Some(0x0),
-        "c" => Some(0xB),
-        "v" => Some(0xF),
-        _ => None,
+impl Default for EmuWasm {
+    fn default() -> Self {
+        Self::new()
     }
 }
+
+fn to_js<E: fmt::Display>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
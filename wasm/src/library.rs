@@ -0,0 +1,164 @@
+// roms fetched over http from the page's own server: by url, e.g. for ?rom=games/pong.ch8 links,
+// or from a library listed in a json file. Everything here returns a Promise, and the quirks and
+// speed come from the rom database if one has been loaded
+//
+// libraries are a list of roms with urls relative to the list and the licence each is shared
+// under, e.g.
+//
+//     [{ "title": "Pong", "file": "pong.ch8", "description": "two players", "license": "CC0" }]
+//
+// web/roms/library.json ships empty. Only add roms that can be redistributed
+
+use crate::{to_js, EmuWasm, State};
+use chip8_core::romdb::RomDb;
+use js_sys::{Function, Promise, Reflect, Uint8Array};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Response, Url};
+
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct LibraryRom {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    license: Option<String>,
+    // made absolute against the library's url once it's loaded
+    file: String,
+}
+
+#[wasm_bindgen]
+impl EmuWasm {
+    // resets and loads the rom at url, resolving once it's running
+    #[wasm_bindgen]
+    pub fn load_url(&mut self, url: &str) -> Result<Promise, JsValue> {
+        load_url(Rc::clone(&self.state), url)
+    }
+
+    // the CHIP-8 community database's programs.json, for titles, quirks and speeds
+    #[wasm_bindgen]
+    pub fn load_romdb_url(&mut self, url: &str) -> Result<Promise, JsValue> {
+        let state = Rc::clone(&self.state);
+        then(&fetch_bytes(url)?, move |body| {
+            let json = String::from_utf8(Uint8Array::new(&body).to_vec()).map_err(to_js)?;
+            state.borrow_mut().romdb = RomDb::from_json(&json).map_err(to_js)?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    // resolves to the number of roms listed
+    #[wasm_bindgen]
+    pub fn load_library_url(&mut self, url: &str) -> Result<Promise, JsValue> {
+        let state = Rc::clone(&self.state);
+        let base = absolute_url(url, None)?;
+        then(&fetch_bytes(&base)?, move |body| {
+            let mut library: Vec<LibraryRom> =
+                serde_json::from_slice(&Uint8Array::new(&body).to_vec()).map_err(to_js)?;
+            for rom in &mut library {
+                rom.file = absolute_url(&rom.file, Some(&base))?;
+            }
+            let len = library.len();
+            state.borrow_mut().library = library;
+            Ok(JsValue::from(len as u32))
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn library_len(&self) -> usize {
+        self.state.borrow().library.len()
+    }
+
+    #[wasm_bindgen]
+    pub fn library_title(&self, index: usize) -> Option<String> {
+        let state = self.state.borrow();
+        state.library.get(index).map(|rom| rom.title.clone())
+    }
+
+    #[wasm_bindgen]
+    pub fn library_description(&self, index: usize) -> Option<String> {
+        let state = self.state.borrow();
+        state.library.get(index)?.description.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn library_license(&self, index: usize) -> Option<String> {
+        let state = self.state.borrow();
+        state.library.get(index)?.license.clone()
+    }
+
+    // resets and loads one of the library's roms, like load_url
+    #[wasm_bindgen]
+    pub fn launch(&mut self, index: usize) -> Result<Promise, JsValue> {
+        let url = self
+            .state
+            .borrow()
+            .library
+            .get(index)
+            .map(|rom| rom.file.clone())
+            .ok_or_else(|| JsValue::from_str(&format!("no rom {} in the library", index)))?;
+        load_url(Rc::clone(&self.state), &url)
+    }
+
+    // the rom database's title for the loaded rom
+    #[wasm_bindgen]
+    pub fn rom_title(&self) -> Option<String> {
+        let state = self.state.borrow();
+        let hash = state.rom_hash.as_deref()?;
+        state.romdb.get(hash).map(|info| info.title.clone())
+    }
+}
+
+fn load_url(state: Rc<RefCell<State>>, url: &str) -> Result<Promise, JsValue> {
+    then(&fetch_bytes(url)?, move |body| {
+        let state = &mut *state.borrow_mut();
+        state.chip8.reset();
+        state
+            .load(&Uint8Array::new(&body).to_vec())
+            .map_err(to_js)?;
+        Ok(JsValue::UNDEFINED)
+    })
+}
+
+// resolves to the body as an ArrayBuffer, or rejects if the server says no
+fn fetch_bytes(url: &str) -> Result<Promise, JsValue> {
+    let window = web_sys::window().ok_or("no window to fetch from")?;
+    let url = url.to_string();
+    then(&window.fetch_with_str(&url), move |response| {
+        let response: Response = response.dyn_into()?;
+        if !response.ok() {
+            return Err(JsValue::from_str(&format!(
+                "{}: {} {}",
+                url,
+                response.status(),
+                response.status_text()
+            )));
+        }
+        Ok(response.array_buffer()?.into())
+    })
+}
+
+// runs f on what the promise resolves to and resolves to whatever f returns, a promise included.
+// An Err rejects
+fn then<F>(promise: &Promise, f: F) -> Result<Promise, JsValue>
+where
+    F: FnOnce(JsValue) -> Result<JsValue, JsValue> + 'static,
+{
+    // through Reflect so the closure is handed over to the promise rather than kept alive here
+    let then: Function = Reflect::get(promise, &JsValue::from_str("then"))?.dyn_into()?;
+    then.call1(promise, &Closure::once_into_js(f))?.dyn_into()
+}
+
+// relative to base, or the page if there isn't one
+fn absolute_url(url: &str, base: Option<&str>) -> Result<String, JsValue> {
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => web_sys::window()
+            .ok_or("no page to find urls from")?
+            .location()
+            .href()?,
+    };
+    Ok(Url::new_with_base(url, &base)?.href())
+}
@@ -0,0 +1,141 @@
+use crate::State;
+use js_sys::Function;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+// the timers run at 60Hz whatever rate the display refreshes at
+const FRAME_MS: f64 = 1000.0 / 60.0;
+// after the tab has been in the background, carry on rather than trying to catch up
+const MAX_ELAPSED_MS: f64 = 250.0;
+
+type Callback = Closure<dyn FnMut(f64) -> Result<(), JsValue>>;
+
+// drives the emulator from requestAnimationFrame. Wall-clock time goes into an accumulator and
+// a 60Hz frame is run for every 1/60s in it, so a 144Hz display sometimes runs none and a 30Hz
+// one runs two
+pub(crate) struct MainLoop {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    state: Rc<RefCell<State>>,
+    // called after any frames have run, with how many, so the page can draw
+    on_frame: RefCell<Option<Function>>,
+    // holds the callback weakly, so dropping the loop drops it too
+    callback: RefCell<Option<Callback>>,
+    running: Cell<bool>,
+    request_id: Cell<Option<i32>>,
+    last_time: Cell<Option<f64>>,
+    accumulator: Cell<f64>,
+}
+
+impl MainLoop {
+    pub fn new(state: Rc<RefCell<State>>) -> Self {
+        let shared = Rc::new(Shared {
+            state,
+            on_frame: RefCell::new(None),
+            callback: RefCell::new(None),
+            running: Cell::new(false),
+            request_id: Cell::new(None),
+            last_time: Cell::new(None),
+            accumulator: Cell::new(0.0),
+        });
+        let weak: Weak<Shared> = Rc::downgrade(&shared);
+        let callback = Callback::new(move |time| match weak.upgrade() {
+            Some(shared) => shared.animation_frame(time),
+            None => Ok(()),
+        });
+        *shared.callback.borrow_mut() = Some(callback);
+        Self { shared }
+    }
+
+    pub fn start(&self, on_frame: Option<Function>) -> Result<(), JsValue> {
+        *self.shared.on_frame.borrow_mut() = on_frame;
+        if !self.shared.running.replace(true) {
+            self.shared.last_time.set(None);
+            self.shared.accumulator.set(0.0);
+            self.shared.request()?;
+        }
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        self.shared.running.set(false);
+        if let Some(id) = self.shared.request_id.take() {
+            if let Some(window) = web_sys::window() {
+                window.cancel_animation_frame(id).ok();
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.shared.running.get()
+    }
+}
+
+impl Drop for MainLoop {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+impl Shared {
+    fn request(&self) -> Result<(), JsValue> {
+        let window = web_sys::window().ok_or("no window to animate in")?;
+        let callback = self.callback.borrow();
+        let callback = callback.as_ref().ok_or("main loop has no callback")?;
+        let id = window.request_animation_frame(callback.as_ref().unchecked_ref())?;
+        self.request_id.set(Some(id));
+        Ok(())
+    }
+
+    fn animation_frame(&self, time: f64) -> Result<(), JsValue> {
+        self.request_id.set(None);
+        if !self.running.get() {
+            return Ok(());
+        }
+        let elapsed = match self.last_time.replace(Some(time)) {
+            Some(last) => (time - last).clamp(0.0, MAX_ELAPSED_MS),
+            None => 0.0,
+        };
+
+        // the state is let go before calling out, so on_frame can use the emulator
+        let frames = {
+            let mut state = self.state.borrow_mut();
+            if state.paused {
+                self.accumulator.set(0.0);
+                0
+            } else {
+                let mut accumulator = self.accumulator.get() + elapsed;
+                let mut frames = 0;
+                // a breakpoint can pause it partway through
+                while accumulator >= FRAME_MS && !state.paused {
+                    let ticks = state.ticks_per_frame;
+                    state.run_frame(ticks);
+                    accumulator -= FRAME_MS;
+                    frames += 1;
+                }
+                self.accumulator.set(accumulator);
+                frames
+            }
+        };
+
+        if frames > 0 {
+            let on_frame = self.on_frame.borrow().clone();
+            if let Some(on_frame) = on_frame {
+                // if it throws the loop stops, the same as a loop written in JS would
+                if let Err(e) = on_frame.call1(&JsValue::NULL, &JsValue::from(frames)) {
+                    self.running.set(false);
+                    return Err(e);
+                }
+            }
+        }
+        // on_frame may have stopped the loop
+        if self.running.get() {
+            self.request()?;
+        }
+        Ok(())
+    }
+}
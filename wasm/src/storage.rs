@@ -0,0 +1,120 @@
+use crate::State;
+use chip8_core::keymap::Keymap;
+use chip8_core::render::{Rgba, NUM_COLORS};
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+use web_sys::Storage;
+
+// everything goes in localStorage under keys starting with this, save states as hex under the
+// rom's sha1 and each setting as its own key
+const PREFIX: &str = "chip8";
+
+pub(crate) struct Store {
+    storage: Storage,
+}
+
+impl Store {
+    // fails if the page has no localStorage, e.g. when the browser blocks it for privacy
+    pub fn local() -> Result<Self, JsValue> {
+        let window = web_sys::window().ok_or("no window to store things in")?;
+        let storage = window
+            .local_storage()?
+            .ok_or("localStorage isn't available")?;
+        Ok(Self { storage })
+    }
+
+    pub fn save_state(&self, rom_hash: &str, state: &[u8]) -> Result<(), JsValue> {
+        self.storage.set_item(&state_key(rom_hash), &to_hex(state))
+    }
+
+    pub fn load_state(&self, rom_hash: &str) -> Result<Option<Vec<u8>>, JsValue> {
+        match self.storage.get_item(&state_key(rom_hash))? {
+            Some(hex) => from_hex(&hex)
+                .map(Some)
+                .ok_or_else(|| JsValue::from_str("the stored save state is corrupt")),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_state(&self, rom_hash: &str) -> Result<(), JsValue> {
+        self.storage.remove_item(&state_key(rom_hash))
+    }
+
+    pub fn save_settings(&self, state: &State) -> Result<(), JsValue> {
+        self.storage
+            .set_item(&setting_key("keymap"), &state.keymap.to_json())?;
+        let palette = state.renderer.palette.colors.map(|[r, g, b, _]| [r, g, b]);
+        self.storage
+            .set_item(&setting_key("palette"), &to_hex(palette.as_flattened()))?;
+        self.storage.set_item(
+            &setting_key("ticks-per-frame"),
+            &state.ticks_per_frame.to_string(),
+        )
+    }
+
+    // applies whichever settings were stored, returning whether there were any
+    pub fn load_settings(&self, state: &mut State) -> Result<bool, JsValue> {
+        let mut found = false;
+        if let Some(json) = self.storage.get_item(&setting_key("keymap"))? {
+            state.keymap = Keymap::from_json(&json).map_err(crate::to_js)?;
+            found = true;
+        }
+        if let Some(hex) = self.storage.get_item(&setting_key("palette"))? {
+            state.renderer.palette.colors = parse_palette(&hex)
+                .ok_or_else(|| JsValue::from_str("the stored palette is corrupt"))?;
+            found = true;
+        }
+        if let Some(ticks) = self.storage.get_item(&setting_key("ticks-per-frame"))? {
+            state.ticks_per_frame = ticks
+                .parse()
+                .map_err(|_| JsValue::from_str("the stored speed is corrupt"))?;
+            found = true;
+        }
+        Ok(found)
+    }
+
+    pub fn clear_settings(&self) -> Result<(), JsValue> {
+        for name in ["keymap", "palette", "ticks-per-frame"] {
+            self.storage.remove_item(&setting_key(name))?;
+        }
+        Ok(())
+    }
+}
+
+fn state_key(rom_hash: &str) -> String {
+    format!("{}.state.{}", PREFIX, rom_hash)
+}
+
+fn setting_key(name: &str) -> String {
+    format!("{}.{}", PREFIX, name)
+}
+
+// rgb for each colour, alpha is always opaque
+fn parse_palette(hex: &str) -> Option<[Rgba; NUM_COLORS]> {
+    let bytes = from_hex(hex)?;
+    if bytes.len() != NUM_COLORS * 3 {
+        return None;
+    }
+    let mut colors = [[0, 0, 0, 0xFF]; NUM_COLORS];
+    for (color, rgb) in colors.iter_mut().zip(bytes.chunks(3)) {
+        color[..3].copy_from_slice(rgb);
+    }
+    Some(colors)
+}
+
+// localStorage only holds strings
+fn to_hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(hex, "{:02x}", b).expect("writing to a String can't fail");
+    }
+    hex
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    // an odd length leaves a last slice that runs off the end
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
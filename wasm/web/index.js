@@ -1,70 +1,221 @@
+// wasm.js and wasm_bg.wasm aren't checked in since they have to match the crate. Build them from
+// the wasm directory with
+//
+//     wasm-pack build --target web --out-dir pkg --out-name wasm
+//     cp pkg/wasm.js pkg/wasm_bg.wasm web/
+//
+// then serve the web directory, e.g. with python3 -m http.server
 import init, * as wasm from "./wasm.js"
 
 const WIDTH = 64
 const HEIGHT = 32
 const SCALE = 15
-const TICKS_PER_FRAME = 10 
-let anim_frame = 0
 
-const canvas = document.getElementById("canvas") 
-canvas.width = WIDTH * SCALE
-canvas.height = HEIGHT * SCALE
+// The canvas is kept at the display's own size and CSS scales it up
+const canvas = document.getElementById("canvas")
+canvas.width = WIDTH
+canvas.height = HEIGHT
+canvas.style.width = `${WIDTH * SCALE}px`
+canvas.style.height = `${HEIGHT * SCALE}px`
+canvas.style.imageRendering = "pixelated"
 
 const ctx = canvas.getContext("2d")
 ctx.fillStyle = "black"
-ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE)
+ctx.fillRect(0, 0, WIDTH, HEIGHT)
 
 const input = document.getElementById("fileinput")
+const palette = document.getElementById("palette")
+const pause = document.getElementById("pause")
+const step = document.getElementById("step")
+const speed = document.getElementById("speed")
+const volume = document.getElementById("volume")
+const mute = document.getElementById("mute")
+const stepInstruction = document.getElementById("step-instruction")
+const debug = document.getElementById("debug")
+const library = document.getElementById("library")
 
-async function run() { 
-    await init() 
+async function run() {
+    const { memory } = await init()
     let chip8 = new wasm.EmuWasm()
-    
-    document.addEventListener("keydown", function(evt) { 
-        chip8.keypress(evt, true)
+
+    // Settings and each rom's progress are kept in localStorage between visits
+    try {
+        chip8.restore_settings()
+    } catch (e) {
+        console.error(e)
+    }
+    function storeSettings() {
+        try {
+            chip8.store_settings()
+        } catch (e) {
+            console.error(e)
+        }
+    }
+    document.addEventListener("visibilitychange", function() {
+        if (document.visibilityState == "hidden" && chip8.rom_hash()) {
+            try {
+                chip8.store_state()
+            } catch (e) {
+                console.error(e)
+            }
+        }
+    })
+
+    // Browsers keep sound off until the page is clicked or typed on
+    function unlockAudio() {
+        if (chip8.audio_state() != "running") {
+            chip8.enable_audio().catch(console.error)
+        }
+    }
+    document.addEventListener("click", unlockAudio)
+    document.addEventListener("keydown", unlockAudio)
+
+    volume.value = chip8.volume()
+    volume.addEventListener("input", function() {
+        chip8.set_volume(Number(volume.value))
+    })
+    mute.addEventListener("change", function() {
+        chip8.set_muted(mute.checked)
+    })
+
+    document.addEventListener("keydown", function(evt) {
+        if (chip8.press_code(evt.code, true)) {
+            evt.preventDefault()
+        }
     })
     document.addEventListener("keyup", function(evt) {
-        chip8.keypress(evt, false)
+        if (chip8.press_code(evt.code, false)) {
+            evt.preventDefault()
+        }
     })
 
-    input.addEventListener("change", function(evt) {
-        // Stop previous game from rendering, if one exists 
-        if (anim_frame != 0) {
-            window.cancelAnimationFrame(anim_frame)
+    palette.addEventListener("change", function() {
+        chip8.set_palette(palette.value)
+        storeSettings()
+        draw(chip8, memory)
+    })
+
+    // The emulator runs its own loop at 60Hz and calls back whenever there's a new frame to draw
+    speed.value = chip8.ticks_per_frame()
+    speed.addEventListener("change", function() {
+        chip8.set_ticks_per_frame(Number(speed.value))
+        storeSettings()
+    })
+    pause.addEventListener("click", function() {
+        chip8.set_paused(!chip8.is_paused())
+        pause.textContent = chip8.is_paused() ? "Resume" : "Pause"
+    })
+    step.addEventListener("click", function() {
+        chip8.advance_frame()
+        pause.textContent = "Resume"
+        draw(chip8, memory)
+    })
+    stepInstruction.addEventListener("click", function() {
+        chip8.step()
+        pause.textContent = "Resume"
+        draw(chip8, memory)
+    })
+
+    // Called once a rom is in, however it got there
+    function play() {
+        try {
+            chip8.restore_state()
+        } catch (e) {
+            console.error(e)
         }
+        const title = chip8.rom_title()
+        if (title) {
+            document.title = `${title} - Chip 8 Emulator`
+        }
+        speed.value = chip8.ticks_per_frame()
+        draw(chip8, memory)
+        chip8.start(() => draw(chip8, memory))
+    }
+
+    input.addEventListener("change", function(evt) {
         let file = evt.target.files[0] 
         if (!file) {
             alert("Failed to read file")
             return
         }
-        alert("read file")
         let fr = new FileReader()
         fr.onload = function(e) {
             let buffer = fr.result
             const rom = new Uint8Array(buffer)
             chip8.reset()
             chip8.load_game(rom)
-            mainloop(chip8)
+            play()
         }
         fr.readAsArrayBuffer(file)
     }, false) 
+
+    library.addEventListener("change", function() {
+        if (library.value !== "") {
+            chip8.launch(Number(library.value)).then(play).catch(alert)
+        }
+    })
+
+    // The rom database is optional, drop programs.json from the CHIP-8 database next to the page
+    // to get titles, quirks and speeds
+    await chip8.load_romdb_url("programs.json").catch(() => {})
+    try {
+        const count = await chip8.load_library_url("roms/library.json")
+        for (let i = 0; i < count; i++) {
+            const option = document.createElement("option")
+            option.value = i
+            option.textContent = chip8.library_title(i)
+            const license = chip8.library_license(i)
+            option.title = [chip8.library_description(i), license && `Licence: ${license}`]
+                .filter(Boolean)
+                .join("\n")
+            library.appendChild(option)
+        }
+        library.hidden = count === 0
+    } catch (e) {
+        console.error(e)
+    }
+
+    // Games can be linked to directly, e.g. index.html?rom=roms/pong.ch8
+    const rom = new URLSearchParams(window.location.search).get("rom")
+    if (rom) {
+        chip8.load_url(rom).then(play).catch(alert)
+    }
 }
-function mainloop(chip8) {
-    // Only draw every few ticks
-    for (let i = 0; i < TICKS_PER_FRAME; i++) {
-        chip8.tick()
+// The framebuffer is read straight out of wasm memory, so drawing is one putImageData
+function draw(chip8, memory) {
+    chip8.render()
+    const width = chip8.display_width()
+    const height = chip8.display_height()
+    if (canvas.width != width || canvas.height != height) {
+        canvas.width = width
+        canvas.height = height
     }
-    // alert("loop")
-    chip8.tick_timers()
-    // Clear the canvas before drawing
-    ctx.fillStyle = "black"
-    ctx.fillRect(0, 0, WIDTH * SCALE, HEIGHT * SCALE)
-    // Set the draw color back to white before we render our frame 
-    ctx.fillStyle = "white"
-    chip8.draw_screen(SCALE)
-    anim_frame = window.requestAnimationFrame(() => { 
-        mainloop(chip8)
-    })
+    const pixels = new Uint8ClampedArray(memory.buffer, chip8.framebuffer_ptr(), chip8.framebuffer_len())
+    ctx.putImageData(new ImageData(pixels, width, height), 0, 0)
+    if (chip8.is_paused()) {
+        showDebug(chip8)
+    } else {
+        debug.textContent = ""
+    }
+}
+
+// The registers and the code around PC, shown while paused
+function showDebug(chip8) {
+    const hex = (n, digits) => n.toString(16).toUpperCase().padStart(digits, "0")
+    const v = Array.from(chip8.v_regs(), (x, i) => `V${hex(i, 1)}=${hex(x, 2)}`)
+    const stack = Array.from(chip8.stack(), (addr) => hex(addr, 3))
+    const pc = chip8.pc()
+    const lines = chip8.disassemble(Math.max(pc - 8, 0), 10)
+        .map((line) => (line.startsWith(hex(pc, 3) + ":") ? "> " : "  ") + line)
+    debug.textContent = [
+        chip8.stop_reason() || "Paused",
+        v.slice(0, 8).join(" "),
+        v.slice(8).join(" "),
+        `PC=${hex(pc, 3)} I=${hex(chip8.i_reg(), 3)} DT=${chip8.delay_timer()} ST=${chip8.sound_timer()}`,
+        `Stack: ${stack.join(" ")}`,
+        "",
+        ...lines,
+    ].join("\n")
 }
 
 run().catch(console.error)
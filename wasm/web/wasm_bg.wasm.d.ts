@@ -1,15 +0,0 @@
-/* tslint:disable */
-/* eslint-disable */
-export const memory: WebAssembly.Memory;
-export function __wbg_emuwasm_free(a: number): void;
-export function emuwasm_new(a: number): void;
-export function emuwasm_tick(a: number): void;
-export function emuwasm_tick_timers(a: number): void;
-export function emuwasm_reset(a: number): void;
-export function emuwasm_keypress(a: number, b: number, c: number): void;
-export function emuwasm_load_game(a: number, b: number): void;
-export function emuwasm_draw_screen(a: number, b: number): void;
-export function __wbindgen_malloc(a: number): number;
-export function __wbindgen_realloc(a: number, b: number, c: number): number;
-export function __wbindgen_add_to_stack_pointer(a: number): number;
-export function __wbindgen_exn_store(a: number): void;